@@ -0,0 +1,163 @@
+// https://tools.ietf.org/html/rfc7616
+use openssl::hash::{hash, MessageDigest};
+
+use crate::http_parser::ResponseHead;
+
+/// Credentials for digest authentication. Every connection first gets a challenge from the server
+/// and then makes the request with the answer.
+#[derive(Debug)]
+pub struct Digest {
+    pub user: String,
+    pub pass: String,
+    pub method: String,
+    pub uri: String,
+}
+
+/// Challenge sent by the server in `WWW-Authenticate: Digest ...`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Challenge {
+    pub realm: String,
+    pub nonce: String,
+    pub opaque: Option<String>,
+    pub algorithm: String,
+    pub qop: Option<String>,
+}
+
+/// Split the comma separated `key=value` pairs of a challenge, values may be quoted.
+fn params(s: &str) -> Vec<(String, String)> {
+    let mut pairs = vec![];
+    let mut rest = s.trim();
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq]
+            .trim()
+            .trim_start_matches(',')
+            .trim()
+            .to_lowercase();
+        rest = rest[eq + 1..].trim_start();
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            let value = quoted[..end].to_owned();
+            rest = &quoted[(end + 1).min(quoted.len())..];
+            value
+        } else {
+            let end = rest.find(',').unwrap_or(rest.len());
+            let value = rest[..end].trim().to_owned();
+            rest = &rest[end..];
+            value
+        };
+        pairs.push((key, value));
+    }
+    pairs
+}
+
+impl Challenge {
+    pub fn parse(header: &str) -> Option<Challenge> {
+        let header = header.trim();
+        if !header
+            .get(..7)
+            .is_some_and(|p| p.eq_ignore_ascii_case("digest "))
+        {
+            return None;
+        }
+        let mut challenge = Challenge {
+            algorithm: "MD5".to_owned(),
+            ..Challenge::default()
+        };
+        for (key, value) in params(&header[7..]) {
+            match key.as_str() {
+                "realm" => challenge.realm = value,
+                "nonce" => challenge.nonce = value,
+                "opaque" => challenge.opaque = Some(value),
+                "algorithm" => challenge.algorithm = value,
+                // only auth is supported as the body is not hashed
+                "qop" if value.split(',').any(|q| q.trim() == "auth") => {
+                    challenge.qop = Some("auth".to_owned())
+                }
+                _ => {}
+            }
+        }
+        if challenge.nonce.is_empty() {
+            return None;
+        }
+        Some(challenge)
+    }
+}
+
+fn hex_digest(digest: MessageDigest, data: &str) -> anyhow::Result<String> {
+    Ok(hash(digest, data.as_bytes())?
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+impl Digest {
+    /// Value of the `Authorization` header answering the challenge.
+    pub fn authorization(&self, challenge: &Challenge, cnonce: &str) -> anyhow::Result<String> {
+        let algorithm = challenge.algorithm.to_uppercase();
+        let md = match algorithm.trim_end_matches("-SESS") {
+            "MD5" => MessageDigest::md5(),
+            "SHA-256" => MessageDigest::sha256(),
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Unsupported digest algorithm {}",
+                    challenge.algorithm
+                ))
+            }
+        };
+        let nc = "00000001";
+        let mut ha1 = hex_digest(
+            md,
+            &format!("{}:{}:{}", self.user, challenge.realm, self.pass),
+        )?;
+        if algorithm.ends_with("-SESS") {
+            ha1 = hex_digest(md, &format!("{}:{}:{}", ha1, challenge.nonce, cnonce))?;
+        }
+        let ha2 = hex_digest(md, &format!("{}:{}", self.method, self.uri))?;
+        let response = match &challenge.qop {
+            Some(qop) => hex_digest(
+                md,
+                &format!(
+                    "{}:{}:{}:{}:{}:{}",
+                    ha1, challenge.nonce, nc, cnonce, qop, ha2
+                ),
+            )?,
+            None => hex_digest(md, &format!("{}:{}:{}", ha1, challenge.nonce, ha2))?,
+        };
+        let mut header = format!(
+            "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={}, response=\"{}\"",
+            self.user, challenge.realm, challenge.nonce, self.uri, challenge.algorithm, response
+        );
+        if let Some(qop) = &challenge.qop {
+            header.push_str(&format!(", qop={}, nc={}, cnonce=\"{}\"", qop, nc, cnonce));
+        }
+        if let Some(opaque) = &challenge.opaque {
+            header.push_str(&format!(", opaque=\"{}\"", opaque));
+        }
+        Ok(header)
+    }
+
+    /// Add the answer to the challenge in response to the request head.
+    pub fn answer(&self, head: &[u8], response: &ResponseHead) -> anyhow::Result<Vec<u8>> {
+        let challenge = match response.all("www-authenticate").find_map(Challenge::parse) {
+            Some(challenge) => challenge,
+            None => {
+                return Err(anyhow::anyhow!(
+                    "Server did not send a digest challenge, got status {}",
+                    response.status
+                ))
+            }
+        };
+        let mut cnonce = [0; 8];
+        openssl::rand::rand_bytes(&mut cnonce)?;
+        let cnonce: String = cnonce.iter().map(|b| format!("{:02x}", b)).collect();
+        let mut head = head.to_vec();
+        head.extend_from_slice(
+            format!(
+                "\r\nAuthorization: {}",
+                self.authorization(&challenge, &cnonce)?
+            )
+            .as_bytes(),
+        );
+        Ok(head)
+    }
+}
@@ -0,0 +1,204 @@
+use std::time::{Duration, Instant};
+
+use crate::dns::Family;
+use crate::executor::{self, Executor, Http, Https};
+use crate::http_parser;
+use crate::report::{Report, Sample};
+use crate::request::{Body, Endpoint};
+use crate::schedule::{self, Limits};
+use crate::tls::TlsConfig;
+
+/// Benchmark of a url driven from rust code, for load tests in your own test suites.
+///
+/// ```no_run
+/// # async fn load_test() -> anyhow::Result<()> {
+/// let report = bust::Benchmark::new("http://127.0.0.1:8080/")
+///     .concurrency(10)
+///     .requests(1000)
+///     .run()
+///     .await?;
+/// assert!(report.percentile(95) < 200);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Benchmark {
+    url: String,
+    method: http::Method,
+    headers: Vec<(String, String)>,
+    body: Option<Vec<u8>>,
+    concurrency: u32,
+    requests: Option<u32>,
+    duration: Option<Duration>,
+    insecure: bool,
+}
+
+impl Benchmark {
+    /// Benchmark of GET requests to the url, one at a time.
+    pub fn new(url: &str) -> Benchmark {
+        Benchmark {
+            url: url.to_owned(),
+            method: http::Method::GET,
+            headers: vec![],
+            body: None,
+            concurrency: 1,
+            requests: None,
+            duration: None,
+            insecure: false,
+        }
+    }
+
+    pub fn method(mut self, method: http::Method) -> Benchmark {
+        self.method = method;
+        self
+    }
+
+    /// Add a header to every request, it is checked when the benchmark is run.
+    pub fn header(mut self, name: &str, value: &str) -> Benchmark {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    pub fn body<B: Into<Vec<u8>>>(mut self, body: B) -> Benchmark {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Number of requests made at the same time.
+    pub fn concurrency(mut self, concurrency: u32) -> Benchmark {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Total number of requests, rounded down to a multiple of the concurrency like `-n`.
+    pub fn requests(mut self, requests: u32) -> Benchmark {
+        self.requests = Some(requests);
+        self
+    }
+
+    /// Keep making requests for this long, the run stops at whichever of the limits comes first.
+    pub fn duration(mut self, duration: Duration) -> Benchmark {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Do not verify the certificate of https servers.
+    pub fn insecure(mut self, insecure: bool) -> Benchmark {
+        self.insecure = insecure;
+        self
+    }
+
+    /// Run the benchmark and return the time of each successful request.
+    pub async fn run(&self) -> anyhow::Result<Report> {
+        self.check()?;
+        let mut builder = http::request::Builder::new()
+            .method(self.method.clone())
+            .uri(self.url.as_str());
+        for (name, value) in &self.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        let req: http::Request<Vec<u8>> = builder.body(vec![])?;
+        let schema = match req.uri().scheme_str() {
+            Some(scheme @ "http") | Some(scheme @ "https") => scheme,
+            _ => return Err(anyhow::anyhow!("Error with protocol")),
+        };
+        let host = match req.uri().host() {
+            Some(host) => host,
+            None => return Err(anyhow::anyhow!("Host not provided")),
+        };
+        let port = match req.uri().port_u16() {
+            Some(port) => port,
+            None if schema == "https" => 443,
+            None => 80,
+        };
+        let head = http_parser::http_string(&req, None)?;
+        let extra = match &self.body {
+            Some(body) => Body::Simple(body.clone()),
+            None => Body::None,
+        };
+        let tls = TlsConfig {
+            insecure: self.insecure,
+            ..TlsConfig::default()
+        };
+        let connector = tls.connector().await?;
+        let lookup = Instant::now();
+        let endpoint = Endpoint::resolve(host, port, Family::Any, None).await?;
+        let lookup = lookup.elapsed().as_millis();
+        let http = Http {
+            endpoint: &endpoint,
+            extra: &extra,
+            decode: false,
+        };
+        let mut report = if schema == "https" {
+            let https = Https {
+                http,
+                connector: &connector,
+                server_name: tls.server_name(host),
+            };
+            self.schedule(&https, &head).await
+        } else {
+            self.schedule(&http, &head).await
+        };
+        report.lookup = lookup;
+        Ok(report)
+    }
+
+    /// Run the benchmark with a custom executor, the body is the request given to it for each
+    /// request and the url is only kept in the report.
+    pub async fn run_with<E: Executor>(&self, executor: &E) -> anyhow::Result<Report> {
+        self.check()?;
+        Ok(self
+            .schedule(executor, self.body.as_deref().unwrap_or_default())
+            .await)
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        if self.requests.is_none() && self.duration.is_none() {
+            return Err(anyhow::anyhow!("Either requests or duration should be set"));
+        }
+        if self.concurrency == 0 {
+            return Err(anyhow::anyhow!("Concurrency should be more than 0"));
+        }
+        Ok(())
+    }
+
+    /// Make the requests, `concurrency` at a time until the limits are reached.
+    async fn schedule<E: Executor>(&self, executor: &E, request: &[u8]) -> Report {
+        let limits = Limits {
+            concurrency: self.concurrency,
+            cycles: self.requests.map(|n| n / self.concurrency),
+            duration: self.duration,
+        };
+        let (mut success, mut failed, mut samples) = (0, 0, vec![]);
+        let run = Instant::now();
+        schedule::run(
+            &limits,
+            None,
+            |_| executor::execute(executor, request),
+            |results| {
+                for result in results {
+                    match result {
+                        Ok(stats) => {
+                            success += 1;
+                            samples.push(Sample::from(&stats));
+                        }
+                        Err(_) => failed += 1,
+                    }
+                }
+            },
+        )
+        .await;
+        Report {
+            args: vec![],
+            url: self.url.clone(),
+            method: self.method.to_string(),
+            concurrency: self.concurrency,
+            success,
+            failed,
+            seconds: run.elapsed().as_secs_f64(),
+            lookup: 0,
+            partial: false,
+            samples,
+        }
+    }
+}
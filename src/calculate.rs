@@ -1,25 +1,183 @@
 use crate::request::Stats;
-use std::cmp::{min,max};
+use std::cmp::{max, min};
 
-pub fn calculate_stats(min_value: &mut Stats, max_value: &mut Stats, c: &Stats, ac: &mut Stats) {
+/// Min, max and sums of the successful requests of a run, for the tables of the report.
+#[derive(Debug)]
+pub struct Totals {
+    pub min: Stats,
+    pub max: Stats,
+    pub ac: Stats,
+    pub sq: Stats,
+    pub count: u128,
+}
+
+impl Default for Totals {
+    fn default() -> Totals {
+        Totals {
+            min: Stats {
+                auth: u128::MAX,
+                connect: u128::MAX,
+                proxy: u128::MAX,
+                handshake: u128::MAX,
+                waiting: u128::MAX,
+                writing: u128::MAX,
+                read: u128::MAX,
+                decode: u128::MAX,
+                compelete: u128::MAX,
+                sent: usize::MAX,
+                length: usize::MAX,
+                header_length: usize::MAX,
+                body_length: usize::MAX,
+                decoded: usize::MAX,
+                ..Stats::default()
+            },
+            max: Stats::default(),
+            ac: Stats::default(),
+            sq: Stats::default(),
+            count: 0,
+        }
+    }
+}
+
+impl Totals {
+    pub fn add(&mut self, c: &Stats) {
+        calculate_stats(&mut self.min, &mut self.max, c, &mut self.ac, &mut self.sq);
+        self.count += 1;
+    }
+
+    /// The min, max, average and standard deviation, all zero when no request was added.
+    pub fn finish(mut self) -> (Stats, Stats, Stats, Stats) {
+        if self.count == 0 {
+            self.min = Stats::default();
+        }
+        let sd = std_dev(&self.ac, &self.sq, self.count);
+        average(&mut self.ac, self.count);
+        (self.min, self.max, self.ac, sd)
+    }
+}
+
+/// Update the min, max and sums with a finished request. `sq` holds the sums of the squares of the
+/// times for the standard deviation.
+pub fn calculate_stats(
+    min_value: &mut Stats,
+    max_value: &mut Stats,
+    c: &Stats,
+    ac: &mut Stats,
+    sq: &mut Stats,
+) {
+    min_value.auth = min(min_value.auth, c.auth);
     min_value.connect = min(min_value.connect, c.connect);
+    min_value.proxy = min(min_value.proxy, c.proxy);
     min_value.handshake = min(min_value.handshake, c.handshake);
     min_value.waiting = min(min_value.waiting, c.waiting);
     min_value.writing = min(min_value.writing, c.writing);
     min_value.read = min(min_value.read, c.read);
+    min_value.decode = min(min_value.decode, c.decode);
     min_value.compelete = min(min_value.compelete, c.compelete);
+    min_value.sent = min(min_value.sent, c.sent);
+    min_value.header_length = min(min_value.header_length, c.header_length);
+    min_value.body_length = min(min_value.body_length, c.body_length);
+    min_value.decoded = min(min_value.decoded, c.decoded);
 
+    max_value.auth = max(max_value.auth, c.auth);
     max_value.connect = max(max_value.connect, c.connect);
+    max_value.proxy = max(max_value.proxy, c.proxy);
     max_value.handshake = max(max_value.handshake, c.handshake);
     max_value.waiting = max(max_value.waiting, c.waiting);
     max_value.writing = max(max_value.writing, c.writing);
     max_value.read = max(max_value.read, c.read);
+    max_value.decode = max(max_value.decode, c.decode);
     max_value.compelete = max(max_value.compelete, c.compelete);
+    max_value.sent = max(max_value.sent, c.sent);
+    max_value.header_length = max(max_value.header_length, c.header_length);
+    max_value.body_length = max(max_value.body_length, c.body_length);
+    max_value.decoded = max(max_value.decoded, c.decoded);
 
+    ac.auth += c.auth;
     ac.connect += c.connect;
+    ac.proxy += c.proxy;
     ac.handshake += c.handshake;
     ac.waiting += c.waiting;
     ac.writing += c.writing;
     ac.read += c.read;
+    ac.decode += c.decode;
     ac.compelete += c.compelete;
+    ac.sent += c.sent;
+    ac.header_length += c.header_length;
+    ac.body_length += c.body_length;
+    ac.decoded += c.decoded;
+
+    sq.auth += c.auth * c.auth;
+    sq.connect += c.connect * c.connect;
+    sq.proxy += c.proxy * c.proxy;
+    sq.handshake += c.handshake * c.handshake;
+    sq.waiting += c.waiting * c.waiting;
+    sq.writing += c.writing * c.writing;
+    sq.read += c.read * c.read;
+    sq.decode += c.decode * c.decode;
+    sq.compelete += c.compelete * c.compelete;
+}
+
+fn deviation(sum: u128, sq: u128, count: u128) -> u128 {
+    if count == 0 {
+        return 0;
+    }
+    let mean = sum as f64 / count as f64;
+    (sq as f64 / count as f64 - mean * mean)
+        .max(0.0)
+        .sqrt()
+        .round() as u128
+}
+
+/// Standard deviation of the time of each phase from the sums and sums of squares of `count` requests.
+pub fn std_dev(ac: &Stats, sq: &Stats, count: u128) -> Stats {
+    Stats {
+        auth: deviation(ac.auth, sq.auth, count),
+        connect: deviation(ac.connect, sq.connect, count),
+        proxy: deviation(ac.proxy, sq.proxy, count),
+        handshake: deviation(ac.handshake, sq.handshake, count),
+        waiting: deviation(ac.waiting, sq.waiting, count),
+        writing: deviation(ac.writing, sq.writing, count),
+        read: deviation(ac.read, sq.read, count),
+        decode: deviation(ac.decode, sq.decode, count),
+        compelete: deviation(ac.compelete, sq.compelete, count),
+        ..Stats::default()
+    }
+}
+
+/// Turn the sums of the times in `ac` into averages over `count` requests. Sizes stay totals.
+pub fn average(ac: &mut Stats, count: u128) {
+    if count == 0 {
+        return;
+    }
+    ac.auth /= count;
+    ac.connect /= count;
+    ac.proxy /= count;
+    ac.handshake /= count;
+    ac.waiting /= count;
+    ac.writing /= count;
+    ac.read /= count;
+    ac.decode /= count;
+    ac.compelete /= count;
+}
+
+/// Min, average and max of the values, all zero when there are none.
+pub fn min_avg_max(values: &[u128]) -> (u128, u128, u128) {
+    match (values.iter().min(), values.iter().max()) {
+        (Some(min), Some(max)) => (
+            *min,
+            values.iter().sum::<u128>() / values.len() as u128,
+            *max,
+        ),
+        _ => (0, 0, 0),
+    }
+}
+
+/// Standard deviation of the values, zero when there are none.
+pub fn std_dev_of(values: &[u128]) -> u128 {
+    deviation(
+        values.iter().sum(),
+        values.iter().map(|v| v * v).sum(),
+        values.len() as u128,
+    )
 }
@@ -0,0 +1,609 @@
+use crate::args_parser::{self, Bust, Command};
+use crate::proxy::ProxyKind;
+use crate::request::{Body, Endpoint};
+use crate::{
+    auth, calculate, compare, config, cookie, dashboard, dns, executor, http_parser, interrupt,
+    multipart, redirect, report, schedule, serve, stream, tables, threshold, timeline, tls,
+    websocket,
+};
+
+/// Run the command given in the arguments of the process.
+pub async fn main() -> anyhow::Result<()> {
+    let argv = config::args_from_env().await?;
+    match args_parser::command_from_argv(&argv) {
+        Command::Run(args) => {
+            let skip = if argv[1] == "run" { 2 } else { 1 };
+            benchmark(args, &argv[skip..]).await
+        }
+        Command::Compare(args) => compare::compare(&args.before, &args.after).await,
+        Command::Replay(args) => replay(args).await,
+        Command::Serve(args) => serve::serve(args).await,
+        Command::Report(args) => report::show(&args.report).await,
+    }
+}
+
+/// Run the benchmark saved in a report again, with `--json` of the saved arguments replaced.
+async fn replay(args: args_parser::Replay) -> anyhow::Result<()> {
+    let report = report::Report::load(&args.report).await?;
+    if report.args.is_empty() {
+        return Err(anyhow::anyhow!(
+            "{} has no arguments to replay",
+            args.report.display()
+        ));
+    }
+    let mut saved = report.args.iter();
+    let mut argv = vec![];
+    while let Some(arg) = saved.next() {
+        if arg == "--json" {
+            saved.next();
+        } else {
+            argv.push(arg.clone());
+        }
+    }
+    if let Some(path) = args.json {
+        argv.push("--json".to_owned());
+        argv.push(path.to_string_lossy().into_owned());
+    }
+    let bust: Bust = args_parser::parse_or_exit("bust replay", &argv);
+    benchmark(bust, &argv).await
+}
+
+/// Run the benchmark and print the report, `argv` are the arguments it was parsed from.
+async fn benchmark(args: Bust, argv: &[String]) -> anyhow::Result<()> {
+    let t = std::time::Instant::now();
+    let method = match args.method {
+        Some(method) => method,
+        None => http::Method::GET,
+    };
+    let mut req: http::Request<Vec<u8>> = http::request::Builder::new()
+        .method(method)
+        .uri(args.url)
+        .body(vec![])?;
+    let heads = req.headers_mut();
+    let cookies = cookie::CookieJar::from_args(&args.cookies);
+    let cookie_jar = args.cookie_jar;
+    // with a jar the cookie header is added to each request by its worker
+    if !cookie_jar {
+        if let Some(c) = cookies.header() {
+            heads.insert(
+                http::header::COOKIE,
+                http::header::HeaderValue::from_str(c.as_str())?,
+            );
+        }
+    }
+    for v in args.headers {
+        heads.append(v.key, v.value);
+    }
+    if let Some(path) = &args.headers_file {
+        let contents = tokio::fs::read_to_string(path).await?;
+        for v in args_parser::parse_headers_file(&contents)? {
+            heads.append(v.key, v.value);
+        }
+    }
+    if let Some(agent) = &args.user_agent {
+        heads.insert(
+            http::header::USER_AGENT,
+            http::header::HeaderValue::from_str(agent)?,
+        );
+    }
+    if args.compressed {
+        heads
+            .entry(http::header::ACCEPT_ENCODING)
+            .or_insert(http::header::HeaderValue::from_static("gzip, deflate, br"));
+    }
+    if args.auth.is_some() && heads.contains_key(http::header::AUTHORIZATION) {
+        return Err(anyhow::anyhow!(
+            "-a can not be used with an Authorization header"
+        ));
+    }
+    if let Some(token) = &args.bearer {
+        if args.auth.is_some() {
+            return Err(anyhow::anyhow!("--bearer can not be used with -a"));
+        }
+        heads.insert(
+            http::header::AUTHORIZATION,
+            http::header::HeaderValue::from_str(&format!("Bearer {}", token))?,
+        );
+    }
+    let file = match args.file {
+        Some(expr) => {
+            heads.insert(
+                "content-type",
+                http::header::HeaderValue::from_str(
+                    "multipart/form-data; boundary=----------------123456789",
+                )?,
+            );
+            let data = multipart::get_file_as_parts(
+                "----------------123456789",
+                expr.key.as_str(),
+                expr.value.as_str(),
+            )
+            .await?;
+            Body::File(data.0, data.1, data.2)
+        }
+        None => match args.data {
+            Some(expr) => Body::Simple(expr.bytes().collect()),
+            None => Body::None,
+        },
+    };
+    let websocket = matches!(req.uri().scheme_str(), Some("ws") | Some("wss"));
+    if args.stream {
+        if websocket {
+            return Err(anyhow::anyhow!(
+                "--stream can not be used with a websocket url"
+            ));
+        }
+        if args.hold == 0 {
+            return Err(anyhow::anyhow!(
+                "--stream requires --hold seconds to read each stream"
+            ));
+        }
+        if args.digest || args.follow_redirects || cookie_jar {
+            return Err(anyhow::anyhow!(
+                "--digest, --follow-redirects and --cookie-jar can not be used with --stream"
+            ));
+        }
+        if !args.fail_if.is_empty()
+            || args.json.is_some()
+            || args.timeline
+            || args.timeline_file.is_some()
+        {
+            return Err(anyhow::anyhow!(
+                "--fail-if, --json, --timeline and --timeline-file can not be used with --stream"
+            ));
+        }
+    }
+    if websocket {
+        if matches!(file, Body::File(..)) || args.digest || args.follow_redirects {
+            return Err(anyhow::anyhow!(
+                "-f, --digest and --follow-redirects can not be used with a websocket url"
+            ));
+        }
+        if !args.fail_if.is_empty()
+            || args.json.is_some()
+            || args.timeline
+            || args.timeline_file.is_some()
+            || cookie_jar
+        {
+            return Err(anyhow::anyhow!(
+                "--fail-if, --json, --timeline, --timeline-file and --cookie-jar can not be used with a websocket url"
+            ));
+        }
+        if let Some(proxy) = &args.proxy {
+            if proxy.kind == ProxyKind::Http && req.uri().scheme_str() == Some("ws") {
+                return Err(anyhow::anyhow!(
+                    "ws:// can only go through a socks5 proxy, use wss://"
+                ));
+            }
+        }
+        if args
+            .message_rate
+            .is_some_and(|rate| rate <= 0.0 || !rate.is_finite())
+        {
+            return Err(anyhow::anyhow!("--message-rate should be more than 0"));
+        }
+        let heads = req.headers_mut();
+        heads.insert(
+            http::header::UPGRADE,
+            http::header::HeaderValue::from_static("websocket"),
+        );
+        heads.insert(
+            http::header::CONNECTION,
+            http::header::HeaderValue::from_static("Upgrade"),
+        );
+        heads.insert(
+            http::header::SEC_WEBSOCKET_VERSION,
+            http::header::HeaderValue::from_static("13"),
+        );
+    }
+    if let Some(proxy) = &args.proxy {
+        if proxy.kind == ProxyKind::Http && req.uri().scheme_str() == Some("http") {
+            if let Some(auth) = proxy.authorization() {
+                req.headers_mut().insert(
+                    http::header::PROXY_AUTHORIZATION,
+                    http::header::HeaderValue::from_str(&auth)?,
+                );
+            }
+        }
+    }
+    let schema = match req.uri().scheme_str() {
+        Some(scheme) => scheme,
+        None => return Err(anyhow::anyhow!("Error the protocol")),
+    };
+    let host = match req.uri().host() {
+        Some(host) => host,
+        None => return Err(anyhow::anyhow!("Host not provided")),
+    };
+    let family = dns::Family::from_switches(args.ipv4, args.ipv6)?;
+    // a shorter interval makes more buckets than a timeline can show, most of them empty
+    if args.interval < 0.01 || !args.interval.is_finite() {
+        return Err(anyhow::anyhow!(
+            "--interval should be at least 0.01 seconds"
+        ));
+    }
+    let tls_config = tls::TlsConfig {
+        cacert: args.cacert,
+        insecure: args.insecure,
+        cert: args.cert,
+        key: args.key,
+        cert_password: args.cert_password,
+        sni: args.sni,
+        resume: args.tls_resume,
+    };
+    let connector = tls_config.connector().await?;
+    let cert_warn_days = args.cert_warn_days;
+    let server_name = tls_config.server_name(host);
+    let absolute = match &args.proxy {
+        Some(proxy) => proxy.kind == ProxyKind::Http && schema == "http",
+        None => false,
+    };
+    let (basic, digest) = match (args.auth, args.digest) {
+        (Some(auth), true) => {
+            let (user, pass) = match auth.find(':') {
+                Some(colon) => (auth[..colon].to_owned(), auth[colon + 1..].to_owned()),
+                None => (auth, String::new()),
+            };
+            let uri = if absolute {
+                req.uri().to_string()
+            } else {
+                req.uri()
+                    .path_and_query()
+                    .map(|p| p.to_string())
+                    .unwrap_or_default()
+            };
+            let digest = auth::Digest {
+                user,
+                pass,
+                method: req.method().to_string(),
+                uri,
+            };
+            (None, Some(digest))
+        }
+        (None, true) => return Err(anyhow::anyhow!("--digest requires credentials with -a")),
+        (auth, false) => (auth, None),
+    };
+    let body = if absolute {
+        http_parser::http_proxy_string(&req, basic.clone())?
+    } else {
+        http_parser::http_string(&req, basic.clone())?
+    };
+    let port = match &req.uri().port() {
+        Some(port) => port.as_u16(),
+        None => match schema {
+            "https" | "wss" => 443,
+            "http" | "ws" => 80,
+            _ => return Err(anyhow::anyhow!("Error while creating ip")),
+        },
+    };
+    let lookup = std::time::Instant::now();
+    let endpoint = match (&args.unix_socket, &args.proxy) {
+        (Some(_), Some(_)) => {
+            return Err(anyhow::anyhow!(
+                "--unix-socket can not be used with --proxy"
+            ))
+        }
+        (Some(path), None) => Endpoint::Unix(path.clone()),
+        (None, proxy) => Endpoint::resolve(host, port, family, proxy.as_ref()).await?,
+    };
+    let lookup_time = lookup.elapsed().as_millis();
+    let redirects = if args.follow_redirects {
+        Some(redirect::Redirects {
+            max: args.max_redirects,
+            req: &req,
+            endpoint: &endpoint,
+            extra: &file,
+            connector: &connector,
+            server_name,
+            family,
+            proxy: args.proxy.as_ref(),
+            auth: basic,
+            digest: digest.as_ref(),
+            decode: args.compressed,
+        })
+    } else {
+        None
+    };
+    let mut totals = calculate::Totals::default();
+    let mut fail = 0;
+    let mut success = 0;
+    let mut over_ipv6 = 0;
+    let mut undecoded = 0;
+    let mut full_handshakes = vec![];
+    let mut resumed_handshakes = vec![];
+    let mut chains = vec![];
+    let jars = std::cell::RefCell::new(vec![cookies; args.concurrency as usize]);
+    if args.total_request.is_none() && args.duration.is_none() {
+        return Err(anyhow::anyhow!(
+            "Either -n or --duration should be provided"
+        ));
+    }
+    if args.concurrency == 0 {
+        return Err(anyhow::anyhow!("Concurrency should be more than 0"));
+    }
+    let concurrency = args.concurrency;
+    let duration = args.duration.map(std::time::Duration::from_secs);
+    let limits = schedule::Limits {
+        concurrency,
+        cycles: args.total_request.map(|n| n / concurrency),
+        duration,
+    };
+    let progress = dashboard::Progress::new(args.total_request, duration);
+    let view = dashboard::Dashboard::start(progress.clone());
+    let interrupt = interrupt::Interrupt::listen();
+    let mut compeleted = vec![];
+    let mut samples = vec![];
+    let save_json = args.json.is_some();
+    let mut saved = vec![];
+    let http = executor::Http {
+        endpoint: &endpoint,
+        extra: &file,
+        decode: args.compressed,
+    };
+    let https = match schema {
+        "http" | "ws" => None,
+        "https" | "wss" => Some(executor::Https {
+            http,
+            connector: &connector,
+            server_name,
+        }),
+        _ => return Err(anyhow::anyhow!("Error with protocol")),
+    };
+    if websocket {
+        let options = websocket::Options {
+            limits,
+            payload: match &file {
+                Body::Simple(data) => data.clone(),
+                _ => vec![b'x'; args.message_size],
+            },
+            messages: args.messages,
+            rate: args.message_rate,
+            hold: std::time::Duration::from_secs(args.hold),
+        };
+        let run = std::time::Instant::now();
+        let sessions = match &https {
+            Some(https) => {
+                websocket::benchmark(https, &body, &options, &interrupt, &progress).await
+            }
+            None => websocket::benchmark(&http, &body, &options, &interrupt, &progress).await,
+        };
+        let run_time = run.elapsed().as_secs_f64();
+        view.stop().await;
+        print_target(req.uri(), port, t.elapsed(), interrupt.is_set());
+        websocket::print(&sessions, &options, run_time, https.is_some());
+        return Ok(());
+    }
+    if args.stream {
+        let options = stream::Options {
+            limits,
+            hold: std::time::Duration::from_secs(args.hold),
+        };
+        let run = std::time::Instant::now();
+        let streams = match &https {
+            Some(https) => stream::benchmark(https, &body, &options, &interrupt, &progress).await,
+            None => stream::benchmark(&http, &body, &options, &interrupt, &progress).await,
+        };
+        let run_time = run.elapsed().as_secs_f64();
+        view.stop().await;
+        print_target(req.uri(), port, t.elapsed(), interrupt.is_set());
+        stream::print(&streams, run_time, https.is_some());
+        return Ok(());
+    }
+    let run = std::time::Instant::now();
+    // run until -n requests are made or the duration is over, whichever comes first
+    schedule::run(
+        &limits,
+        Some(&interrupt),
+        |i| {
+            // each request works on a copy of the jar of its worker, to store the cookies of its
+            // redirects
+            let mut jar = if cookie_jar {
+                Some(jars.borrow()[i].clone())
+            } else {
+                None
+            };
+            let (https, digest, redirects) = (https.as_ref(), digest.as_ref(), redirects.as_ref());
+            let (http, body, progress) = (&http, &body, &progress);
+            async move {
+                let head = jar.as_ref().map(|jar| jar.apply(body));
+                let head = head.as_deref().unwrap_or(body);
+                let result = match https {
+                    Some(https) => executor::execute_with_digest(https, head, digest).await,
+                    None => executor::execute_with_digest(http, head, digest).await,
+                };
+                let result = match (result, redirects) {
+                    (Ok(stats), Some(redirects)) => redirects.follow(stats, jar.as_mut()).await,
+                    (result, _) => result,
+                };
+                progress.record(result.as_ref().ok().map(|c| c.compelete));
+                (i, result, run.elapsed(), jar)
+            }
+        },
+        |s| {
+            s.iter().for_each(|(i, ele, at, jar)| {
+                if let Some(jar) = jar {
+                    jars.borrow_mut()[*i] = jar.clone();
+                }
+                samples.push(timeline::Sample {
+                    at: *at,
+                    latency: ele.as_ref().ok().map(|c| c.compelete),
+                });
+                match ele {
+                    Ok(c) => {
+                        success += 1;
+                        if cookie_jar {
+                            jars.borrow_mut()[*i].store(&c.head);
+                        }
+                        if c.ipv6 {
+                            over_ipv6 += 1;
+                        }
+                        if c.decode_failed {
+                            undecoded += 1;
+                        }
+                        if !c.redirects.is_empty() {
+                            chains.push(c.redirects.clone());
+                        }
+                        if https.is_some() {
+                            if c.resumed {
+                                resumed_handshakes.push(c.handshake);
+                            } else {
+                                full_handshakes.push(c.handshake);
+                            }
+                        }
+                        compeleted.push(c.compelete);
+                        if save_json {
+                            saved.push(report::Sample::from(c));
+                        }
+                        totals.add(c)
+                    }
+                    Err(_) => {
+                        fail += 1;
+                    }
+                }
+            });
+        },
+    )
+    .await;
+
+    let run_time = run.elapsed().as_secs_f64();
+    view.stop().await;
+    let partial = interrupt.is_set();
+    print_target(req.uri(), port, t.elapsed(), partial);
+    match &endpoint {
+        Endpoint::Unix(path) => println!(" Unix Socket     : {}\n", path.display()),
+        Endpoint::Proxy { proxy, .. } => {
+            println!(" Proxy           : {}:{}\n", proxy.host, proxy.port)
+        }
+        Endpoint::Tcp(_) => {}
+    }
+    report::print_counts(success, fail);
+    if let Endpoint::Tcp(_) | Endpoint::Proxy { .. } = &endpoint {
+        println!(" Connected over IPv4         : {}", success - over_ipv6);
+        println!(" Connected over IPv6         : {}", over_ipv6);
+    }
+    if args.compressed {
+        println!(" Failed to Decode            : {}", undecoded);
+    }
+    if redirects.is_some() {
+        println!(" Redirected Request          : {}", chains.len());
+        println!(
+            " Redirects Followed          : {}",
+            chains.iter().map(|c| c.len()).sum::<usize>()
+        );
+    }
+    compeleted.sort();
+    let (min, max, ac, sd) = totals.finish();
+    println!(
+        "\n Requests per second         : {:.2}",
+        success as f64 / run_time
+    );
+    println!(
+        " Received                    : {:.2} MB/s",
+        (ac.header_length + ac.body_length) as f64 / run_time / 1_000_000.0
+    );
+    println!(
+        " Sent                        : {:.2} MB/s",
+        ac.sent as f64 / run_time / 1_000_000.0
+    );
+
+    let handshakes = if tls_config.resume {
+        Some((full_handshakes.as_slice(), resumed_handshakes.as_slice()))
+    } else {
+        None
+    };
+    let phases = tables::OptionalPhases {
+        proxy: matches!(endpoint, Endpoint::Proxy { .. }),
+        digest: digest.is_some(),
+        decode: args.compressed,
+    };
+    tables::create_task_table(&min, &max, &ac, &sd, lookup_time, handshakes, &phases);
+    println!("\nBytes sent and received for each request");
+    tables::create_size_table(&min, &max, &ac, success, args.compressed);
+    report::print_compeleted(&compeleted);
+    if args.timeline || args.timeline_file.is_some() {
+        let buckets =
+            timeline::buckets(&samples, std::time::Duration::from_secs_f64(args.interval));
+        if args.timeline {
+            println!("\nRequests in every {}s of the run", args.interval);
+            tables::create_timeline_table(&buckets);
+        }
+        if let Some(path) = &args.timeline_file {
+            timeline::write_csv(path, &buckets).await?;
+        }
+    }
+    if let Some(path) = &args.json {
+        let report = report::Report {
+            args: argv.to_vec(),
+            url: req.uri().to_string(),
+            method: req.method().to_string(),
+            concurrency,
+            success,
+            failed: fail,
+            seconds: run_time,
+            lookup: lookup_time,
+            partial,
+            samples: saved,
+        };
+        report.save(path).await?;
+    }
+    if !chains.is_empty() {
+        println!("\nTime taken by each redirect");
+        tables::create_redirect_table(&chains);
+    }
+    if schema == "https" && !partial {
+        // the details need a handshake of their own, failing it does not fail the benchmark
+        match tls::inspect(&connector, server_name, &endpoint).await {
+            Ok(info) => {
+                println!("\nTls details of {}", server_name);
+                tables::create_tls_table(&info);
+                for cert in info.chain.iter().filter(|c| c.days_left < cert_warn_days) {
+                    if cert.days_left < 0 {
+                        println!(" Warning: certificate {} has expired", cert.subject);
+                    } else {
+                        println!(
+                            " Warning: certificate {} expires in {} days",
+                            cert.subject, cert.days_left
+                        );
+                    }
+                }
+            }
+            Err(err) => println!("\n Warning: could not read the tls details: {}", err),
+        }
+    }
+    if !args.fail_if.is_empty() {
+        let measured = threshold::Measured {
+            compeleted: &compeleted,
+            error_rate: match success + fail {
+                0 => 0.0,
+                total => fail as f64 * 100.0 / total as f64,
+            },
+            rps: success as f64 / run_time,
+        };
+        println!("\nThresholds");
+        tables::create_threshold_table(&args.fail_if, &measured);
+        let failed: Vec<String> = args
+            .fail_if
+            .iter()
+            .filter(|t| t.failed(&measured))
+            .map(|t| t.to_string())
+            .collect();
+        if !failed.is_empty() {
+            return Err(anyhow::anyhow!("Threshold failed: {}", failed.join(", ")));
+        }
+    }
+    Ok(())
+}
+
+/// Print what was benchmarked and how long it took, after a banner if the run was interrupted.
+fn print_target(uri: &http::Uri, port: u16, taken: std::time::Duration, partial: bool) {
+    if partial {
+        println!(" Interrupted, the report below is partial\n");
+    }
+    println!(
+        " Schema          : {}\n Hostname        : {}\n Path            : {}\n Port            : {}\n",
+        uri.scheme_str().unwrap_or_default(),
+        uri.host().unwrap_or_default(),
+        uri.path(),
+        port
+    );
+    println!("Time taken for bench Marking : {}s\n", taken.as_secs());
+}
@@ -0,0 +1,168 @@
+use std::path::Path;
+
+use crate::report::{Report, Sample};
+use crate::tables;
+
+/// Differences with a p-value below this are reported as significant.
+pub const ALPHA: f64 = 0.05;
+const PERCENTILES: [usize; 5] = [50, 75, 90, 95, 99];
+
+/// Change of one measurement between the two reports, values are in milliseconds.
+#[derive(Debug, PartialEq)]
+pub struct Delta {
+    pub name: String,
+    pub before: f64,
+    pub after: f64,
+    /// Two sided p-value of the Mann-Whitney U test, none when it was not tested.
+    pub p_value: Option<f64>,
+}
+
+impl Delta {
+    /// Change in percent of the before value, none when it was zero.
+    pub fn change(&self) -> Option<f64> {
+        if self.before == 0.0 {
+            return None;
+        }
+        Some((self.after - self.before) * 100.0 / self.before)
+    }
+
+    pub fn significant(&self) -> bool {
+        self.p_value.is_some_and(|p| p < ALPHA)
+    }
+}
+
+// Abramowitz and Stegun 7.1.26, good to about 1e-7 which is plenty for a p-value
+fn erfc(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erfc = poly * (-x * x).exp();
+    if x < 0.0 {
+        2.0 - erfc
+    } else {
+        erfc
+    }
+}
+
+/// Two sided p-value of the Mann-Whitney U test that the samples come from the same distribution.
+/// Uses the normal approximation with tie and continuity correction, none when a side is empty.
+pub fn mann_whitney(a: &[u128], b: &[u128]) -> Option<f64> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let mut all: Vec<(u128, bool)> = a
+        .iter()
+        .map(|v| (*v, true))
+        .chain(b.iter().map(|v| (*v, false)))
+        .collect();
+    all.sort_unstable_by_key(|(v, _)| *v);
+    let mut rank_sum = 0.0;
+    let mut ties = 0.0;
+    let mut i = 0;
+    while i < all.len() {
+        let mut j = i;
+        while j < all.len() && all[j].0 == all[i].0 {
+            j += 1;
+        }
+        // equal values share the average of the ranks i + 1 to j
+        let rank = (i + 1 + j) as f64 / 2.0;
+        let tied = (j - i) as f64;
+        ties += tied * tied * tied - tied;
+        rank_sum += rank * all[i..j].iter().filter(|(_, from_a)| *from_a).count() as f64;
+        i = j;
+    }
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+    let n = n1 + n2;
+    let u = rank_sum - n1 * (n1 + 1.0) / 2.0;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - ties / (n * (n - 1.0)));
+    if variance <= 0.0 {
+        // every value is the same
+        return Some(1.0);
+    }
+    let z = ((u - n1 * n2 / 2.0).abs() - 0.5).max(0.0) / variance.sqrt();
+    Some(erfc(z / std::f64::consts::SQRT_2).min(1.0))
+}
+
+/// Value below which `p` percent of the sorted values are, zero when there are none.
+pub fn percentile(sorted: &[u128], p: usize) -> u128 {
+    match sorted.len() {
+        0 => 0,
+        n => sorted[(n * p / 100).min(n - 1)],
+    }
+}
+
+fn mean(values: &[u128]) -> f64 {
+    match values.len() {
+        0 => 0.0,
+        n => values.iter().sum::<u128>() as f64 / n as f64,
+    }
+}
+
+/// Deltas of the mean time of each phase. Phases neither run spent any time in are left out.
+pub fn phase_deltas(before: &Report, after: &Report) -> Vec<Delta> {
+    let names = Sample::default().phases();
+    (0..names.len())
+        .filter_map(|i| {
+            let column = |report: &Report| -> Vec<u128> {
+                report.samples.iter().map(|s| s.phases()[i].1).collect()
+            };
+            let (a, b) = (column(before), column(after));
+            let name = names[i].0;
+            if name != "Compelete" && a.iter().chain(b.iter()).all(|v| *v == 0) {
+                return None;
+            }
+            Some(Delta {
+                name: name.to_owned(),
+                before: mean(&a),
+                after: mean(&b),
+                p_value: mann_whitney(&a, &b),
+            })
+        })
+        .collect()
+}
+
+/// Deltas of the percentiles of the time to compelete a request.
+pub fn percentile_deltas(before: &Report, after: &Report) -> Vec<Delta> {
+    let sorted = |report: &Report| {
+        let mut values: Vec<u128> = report.samples.iter().map(|s| s.compelete).collect();
+        values.sort_unstable();
+        values
+    };
+    let (a, b) = (sorted(before), sorted(after));
+    PERCENTILES
+        .iter()
+        .map(|p| Delta {
+            name: format!("{}%", p),
+            before: percentile(&a, *p) as f64,
+            after: percentile(&b, *p) as f64,
+            p_value: None,
+        })
+        .collect()
+}
+
+/// Print how the run saved in `after` differs from the one saved in `before`.
+pub async fn compare(before: &Path, after: &Path) -> anyhow::Result<()> {
+    let old = Report::load(before).await?;
+    let new = Report::load(after).await?;
+    if old.url != new.url || old.method != new.method {
+        println!(
+            " Warning: comparing {} {} with {} {}\n",
+            old.method, old.url, new.method, new.url
+        );
+    }
+    tables::create_summary_compare_table(&old, &new);
+    println!("\nTime taken by each phase, p-value of the Mann-Whitney U test");
+    tables::create_compare_table("Task", &phase_deltas(&old, &new));
+    println!("\nApprox time Required to compelete % of request");
+    tables::create_compare_table("Percentage of Request", &percentile_deltas(&old, &new));
+    for (path, report) in [(before, &old), (after, &new)] {
+        if report.partial {
+            println!(
+                " Warning: the run saved in {} was interrupted",
+                path.display()
+            );
+        }
+    }
+    Ok(())
+}
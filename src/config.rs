@@ -0,0 +1,212 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use argh::FromArgs;
+
+use crate::args_parser::{Bust, COMMANDS};
+
+/// Files looked for in the current directory by `bust run` when `--config` is not given.
+const DEFAULT_FILES: [&str; 3] = ["bust.toml", "bust.yaml", "bust.yml"];
+/// Benchmark definition read from a TOML or YAML file. Keys are the long names of the options
+/// of `bust` and `url`, options of a scenario replace the ones at the top level.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub scenarios: BTreeMap<String, BTreeMap<String, Value>>,
+    #[serde(flatten)]
+    pub options: BTreeMap<String, Value>,
+}
+
+impl Config {
+    /// Parse the contents of a config file, YAML when the path ends with `.yaml` or `.yml`.
+    pub fn parse(path: &Path, contents: &str) -> anyhow::Result<Config> {
+        let yaml = matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("yaml") | Some("yml")
+        );
+        let parsed = if yaml {
+            serde_yaml::from_str(contents).map_err(|err| err.to_string())
+        } else {
+            toml::from_str(contents).map_err(|err| err.to_string())
+        };
+        match parsed {
+            Ok(config) => Ok(config),
+            Err(err) => Err(anyhow::anyhow!(
+                "Invalid config {}: {}",
+                path.display(),
+                err
+            )),
+        }
+    }
+
+    /// Options of the scenario merged over the top level ones, keys are written with `-`.
+    pub fn options(&self, scenario: Option<&str>) -> anyhow::Result<BTreeMap<String, Value>> {
+        let normalized = |options: &BTreeMap<String, Value>| -> BTreeMap<String, Value> {
+            options
+                .iter()
+                .map(|(key, value)| (key.replace('_', "-"), value.clone()))
+                .collect()
+        };
+        let mut options = normalized(&self.options);
+        if let Some(name) = scenario {
+            match self.scenarios.get(name) {
+                Some(scenario) => options.extend(normalized(scenario)),
+                None => {
+                    let known: Vec<&str> = self.scenarios.keys().map(|k| k.as_str()).collect();
+                    return Err(anyhow::anyhow!(
+                        "No scenario {} in the config, expected one of: {}",
+                        name,
+                        known.join(", ")
+                    ));
+                }
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Flags of `Bust` that do not take a value and the long names of the short flags, read from its
+/// usage so they follow the options.
+pub fn flags() -> (HashSet<String>, Vec<(String, String)>) {
+    let usage = match Bust::from_args(&["run"], &["--help"]) {
+        Err(exit) => exit.output,
+        Ok(_) => String::new(),
+    };
+    let mut shorts = vec![];
+    for line in usage.lines() {
+        let mut names = line.split_whitespace();
+        if let (Some(short), Some(long)) = (names.next(), names.next()) {
+            match short.strip_suffix(',') {
+                Some(short) if short.starts_with('-') && long.starts_with("--") => {
+                    shorts.push((short.to_owned(), long.to_owned()))
+                }
+                _ => {}
+            }
+        }
+    }
+    // --help comes with argh and is not in the usage line
+    let mut switches: HashSet<String> = ["--help".to_owned()].iter().cloned().collect();
+    let mut tokens = usage
+        .lines()
+        .next()
+        .unwrap_or("")
+        .split_whitespace()
+        .peekable();
+    while let Some(token) = tokens.next() {
+        let flag = token.trim_start_matches('[').trim_end_matches(']');
+        if flag.starts_with('-') && !tokens.peek().is_some_and(|next| next.starts_with('<')) {
+            let long = shorts.iter().find(|(short, _)| short == flag);
+            switches.insert(long.map_or(flag, |(_, long)| long.as_str()).to_owned());
+        }
+    }
+    (switches, shorts)
+}
+
+/// Long flags given on the command line and whether the url was given.
+fn given(cli: &[String]) -> (HashSet<String>, bool) {
+    let (switches, shorts) = flags();
+    let mut flags = HashSet::new();
+    let mut url = false;
+    let mut args = cli.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') || arg.len() == 1 {
+            url = true;
+            continue;
+        }
+        let flag = shorts
+            .iter()
+            .find(|(short, _)| short == arg)
+            .map_or(arg.as_str(), |(_, long)| long.as_str());
+        if !switches.contains(flag) {
+            // skip the value of the option
+            args.next();
+        }
+        flags.insert(flag.to_owned());
+    }
+    (flags, url)
+}
+
+fn value_string(key: &str, value: &Value) -> anyhow::Result<String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(n) => Ok(n.to_string()),
+        _ => Err(anyhow::anyhow!("Invalid value for {} in the config", key)),
+    }
+}
+
+/// Turn the options of `Config::options` into arguments added to the command line `cli`. Options
+/// given on the command line replace the ones in the config, except lists like headers which are
+/// added to.
+pub fn merge(options: &BTreeMap<String, Value>, cli: &[String]) -> anyhow::Result<Vec<String>> {
+    let (flags, has_url) = given(cli);
+    let mut args = cli.to_vec();
+    for (key, value) in options {
+        if key == "url" {
+            if !has_url {
+                args.push(value_string(key, value)?);
+            }
+            continue;
+        }
+        let flag = format!("--{}", key);
+        match value {
+            Value::Array(values) => {
+                for value in values {
+                    args.push(flag.clone());
+                    args.push(value_string(key, value)?);
+                }
+            }
+            _ if flags.contains(&flag) => {}
+            Value::Bool(true) => args.push(flag),
+            Value::Bool(false) => {}
+            value => {
+                args.push(flag);
+                args.push(value_string(key, value)?);
+            }
+        }
+    }
+    Ok(args)
+}
+
+/// Arguments of the process with the options of the config file added, for `--config <file>` and
+/// `run <scenario>`. The arguments are returned as they are when no config is used or for the
+/// subcommands other than `run`.
+pub async fn args_from_env() -> anyhow::Result<Vec<String>> {
+    let mut argv: Vec<String> = std::env::args().collect();
+    let run = match argv.get(1).map(String::as_str) {
+        Some("run") => true,
+        Some(command) if COMMANDS.contains(&command) => return Ok(argv),
+        _ => false,
+    };
+    // anything after run that is not an option or a url names a scenario
+    let scenario = match argv.get(2) {
+        Some(arg) if run && !arg.starts_with('-') && !arg.contains("://") => Some(argv.remove(2)),
+        _ => None,
+    };
+    let skip = if run { 2 } else { 1 };
+    let given = argv
+        .iter()
+        .position(|arg| arg == "--config")
+        .and_then(|at| argv.get(at + 1))
+        .map(PathBuf::from);
+    let path = match (given, &scenario) {
+        (Some(path), _) => path,
+        (None, Some(_)) => match DEFAULT_FILES.iter().map(PathBuf::from).find(|p| p.exists()) {
+            Some(path) => path,
+            None => {
+                return Err(anyhow::anyhow!(
+                    "No config found, pass it with --config or create bust.toml"
+                ))
+            }
+        },
+        (None, None) => return Ok(argv),
+    };
+    let contents = tokio::fs::read_to_string(&path).await?;
+    let config = Config::parse(&path, &contents)?;
+    let options = config.options(scenario.as_deref())?;
+    let mut args = argv[..skip].to_vec();
+    args.extend(merge(&options, &argv[skip..])?);
+    Ok(args)
+}
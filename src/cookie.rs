@@ -0,0 +1,89 @@
+// https://tools.ietf.org/html/rfc6265
+use std::time::SystemTime;
+
+use crate::http_parser::ResponseHead;
+
+/// Cookies of one worker. Cookies set by the server are sent back on the next requests of the
+/// same worker, attributes other than `Max-Age` and `Expires` are ignored as every request goes
+/// to the same host.
+#[derive(Debug, Default, Clone)]
+pub struct CookieJar {
+    cookies: Vec<(String, String)>,
+}
+
+/// Split a `name=value` pair, the value may contain `=`.
+fn pair(s: &str) -> Option<(String, String)> {
+    let eq = s.find('=')?;
+    let name = s[..eq].trim();
+    if name.is_empty() {
+        return None;
+    }
+    Some((name.to_owned(), s[eq + 1..].trim().to_owned()))
+}
+
+impl CookieJar {
+    /// Jar holding the cookies passed with `-C`, each one may be `name=value` or `a=1; b=2`.
+    pub fn from_args(cookies: &[String]) -> CookieJar {
+        let mut jar = CookieJar::default();
+        for (name, value) in cookies.iter().flat_map(|c| c.split(';')).filter_map(pair) {
+            jar.set(name, value);
+        }
+        jar
+    }
+
+    fn set(&mut self, name: String, value: String) {
+        match self.cookies.iter_mut().find(|(n, _)| *n == name) {
+            Some(cookie) => cookie.1 = value,
+            None => self.cookies.push((name, value)),
+        }
+    }
+
+    /// Store the `Set-Cookie` headers of a response. Cookies that have expired are removed.
+    pub fn store(&mut self, head: &ResponseHead) {
+        for set_cookie in head.all("set-cookie") {
+            let mut parts = set_cookie.split(';');
+            let (name, value) = match parts.next().and_then(pair) {
+                Some(cookie) => cookie,
+                None => continue,
+            };
+            let expired =
+                parts
+                    .filter_map(pair)
+                    .any(|(key, value)| match key.to_lowercase().as_str() {
+                        "max-age" => value.parse::<i64>().map(|age| age <= 0).unwrap_or(false),
+                        "expires" => httpdate::parse_http_date(&value)
+                            .map(|date| date <= SystemTime::now())
+                            .unwrap_or(false),
+                        _ => false,
+                    });
+            if expired {
+                self.cookies.retain(|(n, _)| *n != name);
+            } else {
+                self.set(name, value);
+            }
+        }
+    }
+
+    /// Value of the `Cookie` header, none when the jar is empty.
+    pub fn header(&self) -> Option<String> {
+        if self.cookies.is_empty() {
+            return None;
+        }
+        Some(
+            self.cookies
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<String>>()
+                .join("; "),
+        )
+    }
+
+    /// Request head with the `Cookie` header of the jar added.
+    pub fn apply(&self, head: &[u8]) -> Vec<u8> {
+        let mut head = head.to_vec();
+        if let Some(cookies) = self.header() {
+            head.extend_from_slice(format!("\r\nCookie: {}", cookies).as_bytes());
+        }
+        head
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::VecDeque;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::compare;
+
+/// Latencies of the requests finished in this window are used for the rolling percentiles.
+const WINDOW: Duration = Duration::from_secs(5);
+/// How often the dashboard is redrawn on a terminal.
+const REFRESH: Duration = Duration::from_millis(250);
+/// How often a line is logged when stdout is not a terminal.
+const LOG_EVERY: Duration = Duration::from_secs(5);
+const BAR_WIDTH: usize = 30;
+
+/// Counts of the running benchmark, updated by every request as it finishes.
+pub struct Progress {
+    started: Instant,
+    total: Option<u32>,
+    duration: Option<Duration>,
+    completed: AtomicUsize,
+    failed: AtomicUsize,
+    recent: Mutex<VecDeque<(Instant, u128)>>,
+}
+
+impl Progress {
+    pub fn new(total: Option<u32>, duration: Option<Duration>) -> Arc<Progress> {
+        Arc::new(Progress {
+            started: Instant::now(),
+            total,
+            duration,
+            completed: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+            recent: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    /// Record a finished request, latency is none when it failed.
+    pub fn record(&self, latency: Option<u128>) {
+        match latency {
+            Some(latency) => {
+                self.completed.fetch_add(1, Ordering::Relaxed);
+                let now = Instant::now();
+                let mut recent = self.recent.lock().unwrap();
+                recent.push_back((now, latency));
+                while let Some((at, _)) = recent.front() {
+                    if now.duration_since(*at) <= WINDOW {
+                        break;
+                    }
+                    recent.pop_front();
+                }
+            }
+            None => {
+                self.failed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Fraction of the run that is done, by requests or by time whichever is further.
+    fn done(&self) -> Option<f64> {
+        let elapsed = self.started.elapsed();
+        let by_requests = self.total.map(|total| {
+            (self.completed.load(Ordering::Relaxed) + self.failed.load(Ordering::Relaxed)) as f64
+                / total.max(1) as f64
+        });
+        let by_time = self
+            .duration
+            .map(|d| elapsed.as_secs_f64() / d.as_secs_f64().max(f64::EPSILON));
+        match (by_requests, by_time) {
+            (Some(a), Some(b)) => Some(a.max(b).min(1.0)),
+            (a, b) => a.or(b).map(|f| f.min(1.0)),
+        }
+    }
+
+    /// Lines describing the current state of the run.
+    fn render(&self) -> Vec<String> {
+        let now = Instant::now();
+        let (rps, p50, p90, p99) = {
+            let recent = self.recent.lock().unwrap();
+            let rps = recent
+                .iter()
+                .filter(|(at, _)| now.duration_since(*at) <= Duration::from_secs(1))
+                .count();
+            let mut latencies: Vec<u128> = recent
+                .iter()
+                .filter(|(at, _)| now.duration_since(*at) <= WINDOW)
+                .map(|(_, latency)| *latency)
+                .collect();
+            latencies.sort_unstable();
+            let percentile = |p| compare::percentile(&latencies, p);
+            (rps, percentile(50), percentile(90), percentile(99))
+        };
+        let completed = self.completed.load(Ordering::Relaxed);
+        let failed = self.failed.load(Ordering::Relaxed);
+        let mut lines = vec![
+            format!(
+                " Elapsed {:.1}s | Completed {} | Failed {} | Current {} req/s",
+                self.started.elapsed().as_secs_f64(),
+                completed,
+                failed,
+                rps
+            ),
+            format!(
+                " Latency of last {}s | 50% {}ms | 90% {}ms | 99% {}ms",
+                WINDOW.as_secs(),
+                p50,
+                p90,
+                p99
+            ),
+        ];
+        if let Some(done) = self.done() {
+            let filled = (done * BAR_WIDTH as f64) as usize;
+            lines.push(format!(
+                " [{}{}] {:.0}%",
+                "#".repeat(filled),
+                "-".repeat(BAR_WIDTH - filled),
+                done * 100.0
+            ));
+        }
+        lines
+    }
+}
+
+/// Live view of the progress, redrawn in place on a terminal and logged as lines otherwise.
+pub struct Dashboard {
+    stop: Arc<AtomicBool>,
+    handle: tokio::task::JoinHandle<()>,
+}
+
+impl Dashboard {
+    pub fn start(progress: Arc<Progress>) -> Dashboard {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let handle = tokio::spawn(async move {
+            let tty = std::io::stdout().is_terminal();
+            let mut drawn = 0;
+            let mut last_log = Instant::now();
+            while !stopped.load(Ordering::Relaxed) {
+                tokio::time::delay_for(REFRESH).await;
+                if tty {
+                    drawn = redraw(drawn, &progress.render());
+                } else if last_log.elapsed() >= LOG_EVERY {
+                    last_log = Instant::now();
+                    println!("{}", progress.render().join(" |"));
+                }
+            }
+            if tty {
+                redraw(drawn, &[]);
+            }
+        });
+        Dashboard { stop, handle }
+    }
+
+    /// Stop updating and clear the dashboard from the terminal.
+    pub async fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.handle.await;
+    }
+}
+
+/// Replace the lines drawn last time with the new lines, returns the number of lines drawn.
+fn redraw(drawn: usize, lines: &[String]) -> usize {
+    let mut out = String::new();
+    if drawn > 0 {
+        // move to the start of the first line drawn last time
+        out.push_str(&format!("\x1b[{}F", drawn));
+    }
+    out.push_str("\x1b[J");
+    for line in lines {
+        out.push_str(line);
+        out.push('\n');
+    }
+    let mut stdout = std::io::stdout();
+    let _ = stdout.write_all(out.as_bytes());
+    let _ = stdout.flush();
+    lines.len()
+}
@@ -0,0 +1,83 @@
+// https://tools.ietf.org/html/rfc7230#section-4.1 and https://tools.ietf.org/html/rfc7231#section-3.1.2.2
+use std::io::Read;
+
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+
+use crate::http_parser::ResponseHead;
+
+/// Join the chunks of a chunked body, trailers after the last chunk are skipped.
+pub fn dechunk(body: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut decoded = vec![];
+    let mut rest = body;
+    loop {
+        let line_end = match rest.windows(2).position(|w| w == b"\r\n") {
+            Some(end) => end,
+            None => return Err(anyhow::anyhow!("Invalid chunked response")),
+        };
+        let line = String::from_utf8_lossy(&rest[..line_end]);
+        // chunk extensions after `;` are ignored
+        let size = line.split(';').next().unwrap_or("").trim();
+        let size = match usize::from_str_radix(size, 16) {
+            Ok(size) => size,
+            Err(_) => return Err(anyhow::anyhow!("Invalid chunk size {}", size)),
+        };
+        rest = &rest[line_end + 2..];
+        if size == 0 {
+            return Ok(decoded);
+        }
+        if rest.len() < size {
+            return Err(anyhow::anyhow!("Chunked response ended early"));
+        }
+        decoded.extend_from_slice(&rest[..size]);
+        rest = rest[size..].strip_prefix(b"\r\n").unwrap_or(&rest[size..]);
+    }
+}
+
+/// Undo one content coding.
+fn decompress(coding: &str, body: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut decoded = vec![];
+    match coding {
+        "gzip" | "x-gzip" => {
+            GzDecoder::new(body).read_to_end(&mut decoded)?;
+        }
+        // deflate should be zlib wrapped but some servers send it raw
+        "deflate" => {
+            if ZlibDecoder::new(body).read_to_end(&mut decoded).is_err() {
+                decoded.clear();
+                DeflateDecoder::new(body).read_to_end(&mut decoded)?;
+            }
+        }
+        "br" => {
+            brotli_decompressor::Decompressor::new(body, 4096).read_to_end(&mut decoded)?;
+        }
+        "identity" | "" => decoded.extend_from_slice(body),
+        _ => return Err(anyhow::anyhow!("Unsupported content encoding {}", coding)),
+    }
+    Ok(decoded)
+}
+
+/// Decode the body of a response as told by its `Transfer-Encoding` and `Content-Encoding` headers.
+pub fn decode_body(head: &ResponseHead, body: &[u8]) -> anyhow::Result<Vec<u8>> {
+    // responses to HEAD requests have the headers but no body
+    if body.is_empty() {
+        return Ok(vec![]);
+    }
+    let chunked = head
+        .all("transfer-encoding")
+        .any(|te| te.to_lowercase().contains("chunked"));
+    let mut decoded = if chunked {
+        dechunk(body)?
+    } else {
+        body.to_vec()
+    };
+    let codings: Vec<String> = head
+        .all("content-encoding")
+        .flat_map(|ce| ce.split(','))
+        .map(|coding| coding.trim().to_lowercase())
+        .collect();
+    // codings are listed in the order they were applied
+    for coding in codings.iter().rev() {
+        decoded = decompress(coding, &decoded)?;
+    }
+    Ok(decoded)
+}
@@ -0,0 +1,85 @@
+use std::net::IpAddr;
+
+use trust_dns_resolver::config::*;
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// Address family the benchmark is allowed to connect over.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Family {
+    Any,
+    V4,
+    V6,
+}
+
+impl Family {
+    pub fn from_switches(ipv4: bool, ipv6: bool) -> anyhow::Result<Family> {
+        match (ipv4, ipv6) {
+            (true, true) => Err(anyhow::anyhow!("-4 and -6 can not be used together")),
+            (true, false) => Ok(Family::V4),
+            (false, true) => Ok(Family::V6),
+            (false, false) => Ok(Family::Any),
+        }
+    }
+
+    fn allows(self, ip: &IpAddr) -> bool {
+        match self {
+            Family::Any => true,
+            Family::V4 => ip.is_ipv4(),
+            Family::V6 => ip.is_ipv6(),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Family::Any => "",
+            Family::V4 => "IPv4 ",
+            Family::V6 => "IPv6 ",
+        }
+    }
+
+    fn strategy(self) -> LookupIpStrategy {
+        match self {
+            Family::Any => LookupIpStrategy::Ipv4AndIpv6,
+            Family::V4 => LookupIpStrategy::Ipv4Only,
+            Family::V6 => LookupIpStrategy::Ipv6Only,
+        }
+    }
+}
+
+/// Parse a literal ip address from the host part of url. IPv6 literals are
+/// written in brackets in urls eg. `http://[::1]:8080/`.
+pub fn literal_ip(host: &str) -> Option<IpAddr> {
+    host.trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+        .ok()
+}
+
+/// Find all the addresses of host for the given family. Literal ips are returned without making a dns query.
+pub async fn lookup(host: &str, family: Family) -> anyhow::Result<Vec<IpAddr>> {
+    let ips = match literal_ip(host) {
+        Some(ip) => vec![ip],
+        None => {
+            let resolver = TokioAsyncResolver::tokio(
+                ResolverConfig::google(),
+                ResolverOpts {
+                    cache_size: 0,
+                    use_hosts_file: false,
+                    ip_strategy: family.strategy(),
+                    ..ResolverOpts::default()
+                },
+            )
+            .await?;
+            resolver.lookup_ip(host).await?.iter().collect()
+        }
+    };
+    let ips: Vec<IpAddr> = ips.into_iter().filter(|ip| family.allows(ip)).collect();
+    if ips.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No {}address found for {}",
+            family.name(),
+            host
+        ));
+    }
+    Ok(ips)
+}
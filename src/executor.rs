@@ -0,0 +1,232 @@
+use std::future::Future;
+use std::time::Instant;
+
+use tokio::prelude::*;
+use tokio_openssl::SslStream;
+
+use crate::auth::Digest;
+use crate::decode;
+use crate::http_parser;
+use crate::request::{open, tunnel, Body, Connection, Endpoint, Stats};
+use crate::tls;
+
+/// Makes the requests of a benchmark, so the scheduling and the report can be used for any
+/// protocol. Each phase records the time it took in `stats`, `compelete` is filled in by `execute`.
+/// Executors and their futures are `Send`, so a benchmark can be spawned on a multi-threaded
+/// runtime.
+pub trait Executor: Sync {
+    /// Connection the request is sent over.
+    type Connection: Send;
+
+    /// Open a connection to the server.
+    fn connect(
+        &self,
+        stats: &mut Stats,
+    ) -> impl Future<Output = anyhow::Result<Self::Connection>> + Send;
+
+    /// Write the request.
+    fn send(
+        &self,
+        con: &mut Self::Connection,
+        request: &[u8],
+        stats: &mut Stats,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    /// Read the response.
+    fn receive(
+        &self,
+        con: &mut Self::Connection,
+        stats: &mut Stats,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    /// Close the connection once the request is timed, dropping it by default.
+    fn close(&self, con: Self::Connection) -> impl Future<Output = ()> + Send {
+        drop(con);
+        async {}
+    }
+}
+
+/// Make one request with the executor and time it.
+pub async fn execute<E: Executor>(executor: &E, request: &[u8]) -> anyhow::Result<Stats> {
+    let start = Instant::now();
+    let mut stats = Stats::default();
+    let mut con = executor.connect(&mut stats).await?;
+    executor.send(&mut con, request, &mut stats).await?;
+    executor.receive(&mut con, &mut stats).await?;
+    stats.compelete = start.elapsed().as_millis();
+    executor.close(con).await;
+    Ok(stats)
+}
+
+/// Make one request, with digest auth the challenge is fetched on a connection of its own first.
+pub async fn execute_with_digest<E: Executor>(
+    executor: &E,
+    request: &[u8],
+    digest: Option<&Digest>,
+) -> anyhow::Result<Stats> {
+    match digest {
+        Some(digest) => {
+            let start = Instant::now();
+            let challenge = execute(executor, request).await?;
+            let head = digest.answer(request, &challenge.head)?;
+            let auth = start.elapsed().as_millis();
+            let mut stats = execute(executor, &head).await?;
+            stats.auth = auth;
+            stats.compelete += auth;
+            Ok(stats)
+        }
+        None => execute(executor, request).await,
+    }
+}
+
+/// Write the request head followed by the body. Every part is written in full, and nothing
+/// follows the body so the bytes sent are the ones counted by `content-length`. A request without
+/// a body ends at the blank line after the head.
+pub async fn write_request<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    request: &[u8],
+    extra: &Body,
+    stats: &mut Stats,
+) -> anyhow::Result<()> {
+    let start = Instant::now();
+    stream.write_all(request).await?;
+    stats.sent = request.len()
+        + match extra {
+            Body::File(head, middle, end) => {
+                let length = format!(
+                    "\r\ncontent-length: {}\r\n\r\n",
+                    head.len() + middle.len() + end.len()
+                );
+                stream.write_all(length.as_bytes()).await?;
+                stream.write_all(head).await?;
+                stream.write_all(middle).await?;
+                stream.write_all(end).await?;
+                length.len() + head.len() + middle.len() + end.len()
+            }
+            Body::Simple(main) => {
+                let length = format!("\r\ncontent-length: {}\r\n\r\n", main.len());
+                stream.write_all(length.as_bytes()).await?;
+                stream.write_all(main).await?;
+                length.len() + main.len()
+            }
+            Body::None => {
+                stream.write_all(b"\r\n\r\n").await?;
+                4
+            }
+        };
+    stats.writing = start.elapsed().as_millis();
+    Ok(())
+}
+
+/// Read the whole response, its body is decoded when `decode` is set.
+async fn read_response<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    decode: bool,
+    stats: &mut Stats,
+) -> anyhow::Result<()> {
+    let start = Instant::now();
+    let mut v = vec![0; 1];
+    stream.read_exact(&mut v).await?;
+    stats.waiting = start.elapsed().as_millis();
+    stream.read_to_end(&mut v).await?;
+    stats.read = start.elapsed().as_millis() - stats.waiting;
+    // a reply that is not http is still a response, it is counted with an empty head
+    let head = http_parser::parse_response_head(&v).unwrap_or_default();
+    stats.length = v.len();
+    stats.header_length = head.length;
+    stats.body_length = v.len() - head.length;
+    if decode {
+        let decoding = Instant::now();
+        // a body that can not be decoded is still a response, it is counted apart
+        match decode::decode_body(&head, &v[head.length..]) {
+            Ok(body) => stats.decoded = body.len(),
+            Err(_) => {
+                stats.decoded = stats.body_length;
+                stats.decode_failed = true;
+            }
+        }
+        stats.decode = decoding.elapsed().as_millis();
+    }
+    stats.head = head;
+    Ok(())
+}
+
+/// Http requests over tcp, a unix socket or a proxy.
+#[derive(Clone, Copy)]
+pub struct Http<'a> {
+    pub endpoint: &'a Endpoint,
+    /// Body sent after the request head.
+    pub extra: &'a Body,
+    /// Decode the body of the responses, for `--compressed`.
+    pub decode: bool,
+}
+
+impl Executor for Http<'_> {
+    type Connection = Box<dyn Connection>;
+
+    async fn connect(&self, stats: &mut Stats) -> anyhow::Result<Self::Connection> {
+        let start = Instant::now();
+        let (mut stream, ipv6) = open(self.endpoint).await?;
+        stats.connect = start.elapsed().as_millis();
+        stats.ipv6 = ipv6;
+        tunnel(self.endpoint, &mut stream, false).await?;
+        stats.proxy = start.elapsed().as_millis() - stats.connect;
+        Ok(stream)
+    }
+
+    async fn send(
+        &self,
+        con: &mut Self::Connection,
+        request: &[u8],
+        stats: &mut Stats,
+    ) -> anyhow::Result<()> {
+        write_request(con, request, self.extra, stats).await
+    }
+
+    async fn receive(&self, con: &mut Self::Connection, stats: &mut Stats) -> anyhow::Result<()> {
+        read_response(con, self.decode, stats).await
+    }
+}
+
+/// Https requests, the tls connection is made over the connection of `Http`.
+#[derive(Clone, Copy)]
+pub struct Https<'a> {
+    pub http: Http<'a>,
+    pub connector: &'a tls::Connector,
+    pub server_name: &'a str,
+}
+
+impl Executor for Https<'_> {
+    type Connection = SslStream<Box<dyn Connection>>;
+
+    async fn connect(&self, stats: &mut Stats) -> anyhow::Result<Self::Connection> {
+        let start = Instant::now();
+        let (mut stream, ipv6) = open(self.http.endpoint).await?;
+        stats.connect = start.elapsed().as_millis();
+        stats.ipv6 = ipv6;
+        tunnel(self.http.endpoint, &mut stream, true).await?;
+        stats.proxy = start.elapsed().as_millis() - stats.connect;
+        let con = self.connector.connect(self.server_name, stream).await?;
+        stats.handshake = start.elapsed().as_millis() - stats.connect - stats.proxy;
+        stats.resumed = con.ssl().session_reused();
+        Ok(con)
+    }
+
+    async fn send(
+        &self,
+        con: &mut Self::Connection,
+        request: &[u8],
+        stats: &mut Stats,
+    ) -> anyhow::Result<()> {
+        write_request(con, request, self.http.extra, stats).await
+    }
+
+    async fn receive(&self, con: &mut Self::Connection, stats: &mut Stats) -> anyhow::Result<()> {
+        read_response(con, self.http.decode, stats).await
+    }
+
+    async fn close(&self, mut con: Self::Connection) {
+        // openssl only keeps the session resumable when the connection is closed with close_notify
+        let _ = con.shutdown().await;
+    }
+}
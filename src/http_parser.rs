@@ -1,12 +1,26 @@
 use http::Request;
 
 pub fn http_string<T>(req: &Request<T>, auth: Option<String>) -> anyhow::Result<Vec<u8>> {
-    let mut headers = String::from("");
+    request_string(req, auth, false)
+}
+
+/// Request for plain http through a proxy, with the whole url as the request target.
+pub fn http_proxy_string<T>(req: &Request<T>, auth: Option<String>) -> anyhow::Result<Vec<u8>> {
+    request_string(req, auth, true)
+}
+
+/// Headers sent with every request unless the user sets them. A header set with an empty value is
+/// not sent, except for `Connection` which is always `Close` unless it asks for an upgrade.
+const DEFAULT_HEADERS: [&str; 3] = ["host", "user-agent", "connection"];
+
+fn request_string<T>(
+    req: &Request<T>,
+    auth: Option<String>,
+    absolute: bool,
+) -> anyhow::Result<Vec<u8>> {
+    let mut headers = vec![];
     let host = match req.uri().host() {
-        Some(v) => match auth {
-            Some(expr) => format!("{}@{}", expr, v),
-            None => v.to_owned(),
-        },
+        Some(v) => v.to_owned(),
         None => {
             return Err(anyhow::anyhow!("host name not found"));
         }
@@ -18,15 +32,92 @@ pub fn http_string<T>(req: &Request<T>, auth: Option<String>) -> anyhow::Result<
             return Err(anyhow::anyhow!("no path in url found"));
         }
     };
-    headers.push_str(
-        &format!(
-            "Host: {}\r\nUser-Agent: Bust/0.0.1\r\nConnection: Close",
-            host
-        ),
-    );
+    let defaults = [("Host", host), ("User-Agent", "Bust/0.0.1".to_owned())];
+    for (name, value) in defaults.iter() {
+        let mut set = req.headers().get_all(*name).iter().peekable();
+        if set.peek().is_none() {
+            headers.push(format!("{}: {}", name, value));
+        }
+        for val in set.filter(|val| !val.is_empty()) {
+            headers.push(format!("{}: {}", name, val.to_str()?));
+        }
+    }
+    // responses are read until the server closes the connection, so only an upgrade keeps it open
+    let upgrade = req
+        .headers()
+        .get_all("connection")
+        .iter()
+        .filter_map(|val| val.to_str().ok())
+        .find(|val| val.to_lowercase().contains("upgrade"));
+    headers.push(format!("Connection: {}", upgrade.unwrap_or("Close")));
+    if let Some(expr) = auth {
+        headers.push(format!("Authorization: Basic {}", base64::encode(expr)));
+    }
     for (key, val) in req.headers() {
-        headers.push_str(&format!("\r\n{}: {}", key, val.to_str().unwrap()))
+        if DEFAULT_HEADERS.contains(&key.as_str()) || val.is_empty() {
+            continue;
+        }
+        headers.push(format!("{}: {}", key, val.to_str()?))
     }
-    let stup = format!("{} {} HTTP/1.1\r\n{}", req.method(), path, headers);
+    let target = if absolute {
+        req.uri().to_string()
+    } else {
+        path.to_string()
+    };
+    let stup = format!(
+        "{} {} HTTP/1.1\r\n{}",
+        req.method(),
+        target,
+        headers.join("\r\n")
+    );
     Ok(stup.into_bytes().to_vec())
 }
+
+/// Status and headers of a response.
+#[derive(Debug, Default, PartialEq)]
+pub struct ResponseHead {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// Length of the head including the empty line.
+    pub length: usize,
+}
+
+impl ResponseHead {
+    /// Value of the first header with name, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Values of all the headers with name, ignoring case.
+    pub fn all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.headers
+            .iter()
+            .filter(move |(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Parse the status line and headers at the start of response. Returns none until the whole head is there.
+pub fn parse_response_head(response: &[u8]) -> Option<ResponseHead> {
+    let end = response.windows(4).position(|w| w == b"\r\n\r\n")?;
+    let head = String::from_utf8_lossy(&response[..end]);
+    let mut lines = head.split("\r\n");
+    let status = lines.next()?.split_whitespace().nth(1)?.parse().ok()?;
+    let headers = lines
+        .filter_map(|line| {
+            let colon = line.find(':')?;
+            Some((
+                line[..colon].trim().to_owned(),
+                line[colon + 1..].trim().to_owned(),
+            ))
+        })
+        .collect();
+    Some(ResponseHead {
+        status,
+        headers,
+        length: end + 4,
+    })
+}
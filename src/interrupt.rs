@@ -0,0 +1,90 @@
+use std::future::Future;
+use std::time::Duration;
+
+use futures::stream::{FuturesUnordered, StreamExt};
+use tokio::sync::watch;
+
+/// How long requests in flight are waited for after an interrupt.
+pub const GRACE: Duration = Duration::from_secs(2);
+
+/// Set when the run is interrupted with ctrl-c or SIGTERM. A second signal exits right away.
+#[derive(Clone)]
+pub struct Interrupt {
+    set: watch::Receiver<bool>,
+}
+
+async fn signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut term = match signal(SignalKind::terminate()) {
+            Ok(term) => term,
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+                return;
+            }
+        };
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = term.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+impl Interrupt {
+    /// Start listening for the signals.
+    pub fn listen() -> Interrupt {
+        let (tx, set) = watch::channel(false);
+        tokio::spawn(async move {
+            signal().await;
+            let _ = tx.broadcast(true);
+            signal().await;
+            std::process::exit(130);
+        });
+        Interrupt { set }
+    }
+
+    pub fn is_set(&self) -> bool {
+        *self.set.borrow()
+    }
+
+    /// Resolves once the run is interrupted.
+    pub async fn wait(&self) {
+        let mut set = self.set.clone();
+        while let Some(interrupted) = set.recv().await {
+            if interrupted {
+                return;
+            }
+        }
+        // the signals are no longer listened for
+        futures::future::pending::<()>().await
+    }
+
+    /// Resolves once the grace period after an interrupt is over.
+    pub async fn grace_over(&self) {
+        self.wait().await;
+        tokio::time::delay_for(GRACE).await;
+    }
+}
+
+/// Wait for all the requests, giving up on the ones still running when the grace period after an
+/// interrupt is over. Outputs are in the order the requests finished.
+pub async fn finish<F: Future>(requests: Vec<F>, interrupt: &Interrupt) -> Vec<F::Output> {
+    let mut running: FuturesUnordered<F> = requests.into_iter().collect();
+    let mut done = Vec::with_capacity(running.len());
+    let grace = interrupt.grace_over();
+    tokio::pin!(grace);
+    loop {
+        tokio::select! {
+            output = running.next() => match output {
+                Some(output) => done.push(output),
+                None => return done,
+            },
+            _ = &mut grace => return done,
+        }
+    }
+}
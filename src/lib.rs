@@ -0,0 +1,40 @@
+#![cfg_attr(test, feature(test))]
+
+mod args_parser;
+mod auth;
+#[cfg(test)]
+mod bench;
+mod benchmark;
+mod calculate;
+pub mod cli;
+mod compare;
+mod config;
+mod cookie;
+mod dashboard;
+mod decode;
+mod dns;
+mod executor;
+mod http_parser;
+mod interrupt;
+mod multipart;
+mod proxy;
+mod redirect;
+mod report;
+mod request;
+mod schedule;
+mod serve;
+mod stream;
+mod tables;
+#[cfg(test)]
+mod test;
+mod threshold;
+mod timeline;
+mod tls;
+mod websocket;
+
+pub use crate::benchmark::Benchmark;
+pub use crate::executor::Executor;
+pub use crate::http_parser::ResponseHead;
+pub use crate::redirect::Hop;
+pub use crate::report::{Report, Sample};
+pub use crate::request::Stats;
@@ -0,0 +1,221 @@
+// https://tools.ietf.org/html/rfc7231#section-6.4
+use std::time::Instant;
+
+use http::header::{AUTHORIZATION, CONTENT_TYPE, COOKIE, HOST, PROXY_AUTHORIZATION};
+use http::{Method, Request, Uri};
+
+use crate::auth::Digest;
+use crate::cookie::CookieJar;
+use crate::dns::Family;
+use crate::executor::{self, Http, Https};
+use crate::http_parser;
+use crate::proxy::{Proxy, ProxyKind};
+use crate::request::{Body, Endpoint, Stats};
+use crate::tls;
+
+/// A redirect that was followed and the time taken for it, including the dns query for a new host.
+#[derive(Debug, Clone)]
+pub struct Hop {
+    pub url: String,
+    pub time: u128,
+}
+
+/// Everything needed to send the request again to the location of a redirect.
+pub struct Redirects<'a> {
+    pub max: u32,
+    /// The first request, its headers are sent on every hop.
+    pub req: &'a Request<Vec<u8>>,
+    pub endpoint: &'a Endpoint,
+    pub extra: &'a Body,
+    pub connector: &'a tls::Connector,
+    pub server_name: &'a str,
+    pub family: Family,
+    pub proxy: Option<&'a Proxy>,
+    /// Basic credentials, only sent to the host of the first request.
+    pub auth: Option<String>,
+    /// Digest credentials, hops to the host of the first request answer a challenge of their own.
+    pub digest: Option<&'a Digest>,
+    /// Decode the body of the responses.
+    pub decode: bool,
+}
+
+fn is_redirect(status: u16) -> bool {
+    matches!(status, 301 | 302 | 303 | 307 | 308)
+}
+
+/// Scheme, host and port of an absolute url.
+fn origin(uri: &Uri) -> anyhow::Result<(&str, &str, u16)> {
+    let scheme = match uri.scheme_str() {
+        Some(scheme @ "http") | Some(scheme @ "https") => scheme,
+        _ => return Err(anyhow::anyhow!("Can not follow redirect to {}", uri)),
+    };
+    let host = match uri.host() {
+        Some(host) => host,
+        None => return Err(anyhow::anyhow!("Host not provided in redirect to {}", uri)),
+    };
+    let port = match uri.port_u16() {
+        Some(port) => port,
+        None if scheme == "https" => 443,
+        None => 80,
+    };
+    Ok((scheme, host, port))
+}
+
+/// Resolve the `Location` of a redirect against the url it was sent for.
+pub fn resolve_location(base: &Uri, location: &str) -> anyhow::Result<Uri> {
+    let location = location.trim();
+    // fragments are never sent to the server
+    let location = location.split('#').next().unwrap_or("");
+    let scheme = base.scheme_str().unwrap_or("http");
+    let authority = base.authority().map(|a| a.as_str()).unwrap_or("");
+    let absolute = if location.contains("://") {
+        location.to_owned()
+    } else if location.starts_with("//") {
+        format!("{}:{}", scheme, location)
+    } else if location.starts_with('/') {
+        format!("{}://{}{}", scheme, authority, location)
+    } else {
+        let path = base.path();
+        let dir = &path[..path.rfind('/').map(|i| i + 1).unwrap_or(0)];
+        format!("{}://{}{}{}", scheme, authority, dir, location)
+    };
+    Ok(absolute.parse()?)
+}
+
+impl<'a> Redirects<'a> {
+    /// Follow the redirects of a finished request. The returned stats are the sum of all the
+    /// hops with the response of the last one. Cookies of each redirect are stored in the jar and
+    /// sent on the hops to the host of the first request.
+    pub async fn follow(
+        &self,
+        first: Stats,
+        mut jar: Option<&mut CookieJar>,
+    ) -> anyhow::Result<Stats> {
+        let mut stats = first;
+        let mut uri = self.req.uri().clone();
+        let mut method = self.req.method().clone();
+        let mut with_body = true;
+        while is_redirect(stats.head.status) {
+            if stats.redirects.len() as u32 >= self.max {
+                return Err(anyhow::anyhow!("Stopped after {} redirects", self.max));
+            }
+            let location = match stats.head.header("location") {
+                Some(location) => location,
+                None => return Err(anyhow::anyhow!("Redirect without a location")),
+            };
+            let next = resolve_location(&uri, location)?;
+            // browsers change the method of a redirected POST to GET
+            let status = stats.head.status;
+            if status == 303 && method != Method::HEAD
+                || (status == 301 || status == 302) && method == Method::POST
+            {
+                method = Method::GET;
+                with_body = false;
+            }
+            if let Some(jar) = jar.as_deref_mut() {
+                jar.store(&stats.head);
+            }
+            let start = Instant::now();
+            let mut hop = self.send(&next, &method, with_body, jar.as_deref()).await?;
+            let time = start.elapsed().as_millis();
+            hop.compelete = time;
+            hop.redirects.push(Hop {
+                url: next.to_string(),
+                time,
+            });
+            stats = stats + hop;
+            uri = next;
+        }
+        Ok(stats)
+    }
+
+    async fn send(
+        &self,
+        uri: &Uri,
+        method: &Method,
+        with_body: bool,
+        jar: Option<&CookieJar>,
+    ) -> anyhow::Result<Stats> {
+        let (scheme, host, port) = origin(uri)?;
+        let (_, first_host, first_port) = origin(self.req.uri())?;
+        let same_host = first_host == host && first_port == port;
+        let mut req: Request<Vec<u8>> = Request::builder()
+            .method(method.clone())
+            .uri(uri.clone())
+            .body(vec![])?;
+        for (key, value) in self.req.headers() {
+            let skip = *key == PROXY_AUTHORIZATION
+                || !same_host && (*key == HOST || *key == AUTHORIZATION || *key == COOKIE)
+                || !with_body && *key == CONTENT_TYPE;
+            if !skip {
+                req.headers_mut().append(key, value.clone());
+            }
+        }
+        let auth = if same_host { self.auth.clone() } else { None };
+        let absolute =
+            matches!(self.proxy, Some(proxy) if proxy.kind == ProxyKind::Http && scheme == "http");
+        let mut head = match self.proxy {
+            Some(proxy) if absolute => {
+                if let Some(auth) = proxy.authorization() {
+                    req.headers_mut().insert(
+                        PROXY_AUTHORIZATION,
+                        http::header::HeaderValue::from_str(&auth)?,
+                    );
+                }
+                http_parser::http_proxy_string(&req, auth)?
+            }
+            _ => http_parser::http_string(&req, auth)?,
+        };
+        let digest = match self.digest {
+            Some(digest) if same_host => Some(Digest {
+                user: digest.user.clone(),
+                pass: digest.pass.clone(),
+                method: method.to_string(),
+                uri: if absolute {
+                    uri.to_string()
+                } else {
+                    uri.path_and_query()
+                        .map(|p| p.to_string())
+                        .unwrap_or_default()
+                },
+            }),
+            _ => None,
+        };
+        if let (true, Some(jar)) = (same_host, jar) {
+            head = jar.apply(&head);
+        }
+        let resolved;
+        let endpoint = if same_host {
+            self.endpoint
+        } else if let Endpoint::Unix(_) = self.endpoint {
+            return Err(anyhow::anyhow!(
+                "Can not follow redirect to {} over a unix socket",
+                host
+            ));
+        } else {
+            resolved = Endpoint::resolve(host, port, self.family, self.proxy).await?;
+            &resolved
+        };
+        let extra = if with_body { self.extra } else { &Body::None };
+        let http = Http {
+            endpoint,
+            extra,
+            decode: self.decode,
+        };
+        if scheme == "https" {
+            let server_name = if same_host {
+                self.server_name
+            } else {
+                host.trim_start_matches('[').trim_end_matches(']')
+            };
+            let https = Https {
+                http,
+                connector: self.connector,
+                server_name,
+            };
+            executor::execute_with_digest(&https, &head, digest.as_ref()).await
+        } else {
+            executor::execute_with_digest(&http, &head, digest.as_ref()).await
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::calculate;
+use crate::compare;
+use crate::request::Stats;
+use crate::tables;
+
+/// Time of each phase of one successful request in milliseconds.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Sample {
+    pub auth: u128,
+    pub connect: u128,
+    pub proxy: u128,
+    pub handshake: u128,
+    pub waiting: u128,
+    pub writing: u128,
+    pub read: u128,
+    pub decode: u128,
+    pub compelete: u128,
+}
+
+impl From<&Stats> for Sample {
+    fn from(c: &Stats) -> Sample {
+        Sample {
+            auth: c.auth,
+            connect: c.connect,
+            proxy: c.proxy,
+            handshake: c.handshake,
+            waiting: c.waiting,
+            writing: c.writing,
+            read: c.read,
+            decode: c.decode,
+            compelete: c.compelete,
+        }
+    }
+}
+
+impl From<&Sample> for Stats {
+    fn from(s: &Sample) -> Stats {
+        Stats {
+            auth: s.auth,
+            connect: s.connect,
+            proxy: s.proxy,
+            handshake: s.handshake,
+            waiting: s.waiting,
+            writing: s.writing,
+            read: s.read,
+            decode: s.decode,
+            compelete: s.compelete,
+            ..Stats::default()
+        }
+    }
+}
+
+impl Sample {
+    /// Phases by name, in the order they happen.
+    pub fn phases(&self) -> [(&'static str, u128); 9] {
+        [
+            ("Digest Challenge", self.auth),
+            ("Connection Time", self.connect),
+            ("Proxy Tunnel Setup", self.proxy),
+            ("Tls Handshake Time", self.handshake),
+            ("Writing the Request", self.writing),
+            ("Waiting For Response", self.waiting),
+            ("Reading the Response", self.read),
+            ("Decoding the Response", self.decode),
+            ("Compelete", self.compelete),
+        ]
+    }
+}
+
+/// Result of a run saved with `--json`, with every sample so runs can be compared later.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    /// Arguments the benchmark was run with, for `bust replay`.
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub url: String,
+    pub method: String,
+    pub concurrency: u32,
+    pub success: usize,
+    pub failed: usize,
+    pub seconds: f64,
+    /// Milliseconds taken by the dns query.
+    #[serde(default)]
+    pub lookup: u128,
+    pub partial: bool,
+    pub samples: Vec<Sample>,
+}
+
+impl Report {
+    pub async fn save(&self, path: &Path) -> anyhow::Result<()> {
+        tokio::fs::write(path, serde_json::to_vec_pretty(self)?).await?;
+        Ok(())
+    }
+
+    pub async fn load(path: &Path) -> anyhow::Result<Report> {
+        let data = tokio::fs::read(path).await?;
+        match serde_json::from_slice(&data) {
+            Ok(report) => Ok(report),
+            Err(err) => Err(anyhow::anyhow!(
+                "{} is not a bust report: {}",
+                path.display(),
+                err
+            )),
+        }
+    }
+
+    pub fn requests_per_second(&self) -> f64 {
+        self.success as f64 / self.seconds.max(f64::EPSILON)
+    }
+
+    /// Milliseconds within which `p` percent of the successful requests compeleted.
+    pub fn percentile(&self, p: usize) -> u128 {
+        let mut compeleted: Vec<u128> = self.samples.iter().map(|s| s.compelete).collect();
+        compeleted.sort_unstable();
+        compare::percentile(&compeleted, p)
+    }
+
+    /// Percentage of the requests that failed.
+    pub fn error_rate(&self) -> f64 {
+        match self.success + self.failed {
+            0 => 0.0,
+            total => self.failed as f64 * 100.0 / total as f64,
+        }
+    }
+}
+
+/// Print the report saved in the file like at the end of the run.
+pub async fn show(path: &Path) -> anyhow::Result<()> {
+    let report = Report::load(path).await?;
+    let mut totals = calculate::Totals::default();
+    for sample in &report.samples {
+        totals.add(&sample.into());
+    }
+    let (min, max, ac, sd) = totals.finish();
+    println!(" Url             : {} {}\n", report.method, report.url);
+    if report.partial {
+        println!(" Interrupted, the report below is partial\n");
+    }
+    println!(" Concurrency                 : {}", report.concurrency);
+    print_counts(report.success, report.failed);
+    println!(
+        " Requests per second         : {:.2}\n",
+        report.requests_per_second()
+    );
+    let any = |phase: fn(&Sample) -> u128| report.samples.iter().any(|s| phase(s) > 0);
+    let phases = tables::OptionalPhases {
+        proxy: any(|s| s.proxy),
+        digest: any(|s| s.auth),
+        decode: any(|s| s.decode),
+    };
+    tables::create_task_table(&min, &max, &ac, &sd, report.lookup, None, &phases);
+    let mut compeleted: Vec<u128> = report.samples.iter().map(|s| s.compelete).collect();
+    compeleted.sort_unstable();
+    print_compeleted(&compeleted);
+    Ok(())
+}
+
+/// Print the number of requests, for a run and for a saved report.
+pub fn print_counts(success: usize, failed: usize) {
+    println!(" Number of Total Request     : {}", success + failed);
+    println!(" Number of Sucessfull Request: {}", success);
+    println!(" Number of Failed Request    : {}", failed);
+}
+
+/// Print the percentiles of the sorted times taken to compelete the requests.
+pub fn print_compeleted(compeleted: &[u128]) {
+    if compeleted.is_empty() {
+        println!("\nNo request was successful");
+    } else {
+        println!("\nApprox time Required to compelete % of request");
+        tables::create_percent_table(compeleted);
+    }
+}
@@ -1,31 +1,183 @@
+use std::fmt::Debug;
 use std::net::SocketAddr;
 use std::ops::Add;
+use std::path::PathBuf;
+use std::time::Duration;
 
+use futures::stream::{FuturesUnordered, StreamExt};
+use tokio::net::TcpStream;
 use tokio::prelude::*;
 
+use crate::dns::{self, Family};
+use crate::http_parser::ResponseHead;
+use crate::proxy::{Proxy, ProxyKind};
+use crate::redirect::Hop;
+
+/// Delay before racing the next address, as suggested by RFC 8305.
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
 #[derive(Debug, Default)]
 pub struct Stats {
+    pub auth: u128,
     pub connect: u128,
+    pub proxy: u128,
     pub handshake: u128,
     pub waiting: u128,
     pub writing: u128,
     pub compelete: u128,
     pub read: u128,
+    pub decode: u128,
+    /// Bytes written for the request.
+    pub sent: usize,
+    /// Bytes read for the response, the head and the body as it came over the wire.
     pub length: usize,
+    pub header_length: usize,
+    pub body_length: usize,
+    /// Length of the body after removing chunked and content encodings.
+    pub decoded: usize,
+    /// Whether the body could not be decoded, `decoded` is then the length of the body.
+    pub decode_failed: bool,
+    pub ipv6: bool,
+    pub resumed: bool,
+    pub head: ResponseHead,
+    /// Redirects followed after the first request.
+    pub redirects: Vec<Hop>,
 }
 
 impl Add for Stats {
     type Output = Self;
     fn add(self, other: Self) -> Self {
         Self {
+            auth: self.auth + other.auth,
             connect: self.connect + other.connect,
+            proxy: self.proxy + other.proxy,
             waiting: self.waiting + other.waiting,
             writing: self.writing + other.writing,
             handshake: self.handshake + other.handshake,
             compelete: self.compelete + other.compelete,
             read: self.read + other.read,
-            length: self.length.max(other.length),
+            decode: self.decode + other.decode,
+            sent: self.sent + other.sent,
+            length: self.length + other.length,
+            header_length: self.header_length + other.header_length,
+            body_length: self.body_length + other.body_length,
+            decoded: other.decoded,
+            decode_failed: other.decode_failed,
+            ipv6: self.ipv6 || other.ipv6,
+            resumed: self.resumed || other.resumed,
+            head: other.head,
+            redirects: self.redirects.into_iter().chain(other.redirects).collect(),
+        }
+    }
+}
+
+/// Where the requests are sent to.
+#[derive(Debug)]
+pub enum Endpoint {
+    /// Addresses of the host, raced with happy eyeballs.
+    Tcp(Vec<SocketAddr>),
+    /// Path of a unix domain socket.
+    Unix(PathBuf),
+    /// Addresses of the proxy and the host and port to reach through it.
+    Proxy {
+        addrs: Vec<SocketAddr>,
+        proxy: Proxy,
+        host: String,
+        port: u16,
+    },
+}
+
+impl Endpoint {
+    /// Look up the addresses of host, or of the proxy when requests go through one.
+    pub async fn resolve(
+        host: &str,
+        port: u16,
+        family: Family,
+        proxy: Option<&Proxy>,
+    ) -> anyhow::Result<Endpoint> {
+        match proxy {
+            Some(proxy) => {
+                let ips = dns::lookup(&proxy.host, family).await?;
+                let host = if proxy.local_dns {
+                    dns::lookup(host, family).await?[0].to_string()
+                } else {
+                    host.to_owned()
+                };
+                Ok(Endpoint::Proxy {
+                    addrs: ips
+                        .into_iter()
+                        .map(|ip| SocketAddr::new(ip, proxy.port))
+                        .collect(),
+                    proxy: proxy.clone(),
+                    host,
+                    port,
+                })
+            }
+            None => {
+                let ips = dns::lookup(host, family).await?;
+                Ok(Endpoint::Tcp(
+                    ips.into_iter()
+                        .map(|ip| SocketAddr::new(ip, port))
+                        .collect(),
+                ))
+            }
+        }
+    }
+}
+
+/// Byte stream to the server, either tcp or unix socket.
+pub trait Connection: AsyncRead + AsyncWrite + Unpin + Send + Debug {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + Debug> Connection for T {}
+
+/// Order addresses for connection attempts by alternating the families, starting with IPv6.
+fn interleave(addrs: &[SocketAddr]) -> Vec<SocketAddr> {
+    let mut v6 = addrs.iter().filter(|a| a.is_ipv6());
+    let mut v4 = addrs.iter().filter(|a| a.is_ipv4());
+    let mut ordered = Vec::with_capacity(addrs.len());
+    loop {
+        match (v6.next(), v4.next()) {
+            (None, None) => return ordered,
+            (a, b) => ordered.extend(a.into_iter().chain(b).copied()),
+        }
+    }
+}
+
+/// Connect to one of the addresses happy eyeballs style. A new attempt is started whenever the
+/// previous one fails or takes longer than `CONNECTION_ATTEMPT_DELAY`, the first to connect wins.
+pub async fn connect(addrs: &[SocketAddr]) -> anyhow::Result<TcpStream> {
+    let mut queue = interleave(addrs).into_iter();
+    let mut attempts = FuturesUnordered::new();
+    let mut last_error = None;
+    loop {
+        if attempts.is_empty() {
+            match queue.next() {
+                Some(addr) => attempts.push(TcpStream::connect(addr)),
+                None => break,
+            }
         }
+        let delay = tokio::time::delay_for(CONNECTION_ATTEMPT_DELAY);
+        tokio::select! {
+            result = attempts.next() => match result {
+                Some(Ok(stream)) => return Ok(stream),
+                Some(Err(err)) => {
+                    last_error = Some(err);
+                    if let Some(addr) = queue.next() {
+                        attempts.push(TcpStream::connect(addr));
+                    }
+                }
+                None => {}
+            },
+            _ = delay => {
+                if let Some(addr) = queue.next() {
+                    attempts.push(TcpStream::connect(addr));
+                }
+            }
+        }
+    }
+    match last_error {
+        Some(err) => Err(err.into()),
+        None => Err(anyhow::anyhow!("No address to connect")),
     }
 }
 
@@ -35,117 +187,37 @@ pub enum Body {
     None,
 }
 
-/// Make https request and bench mark performace of the request. This function uses native tls for https certs.
-pub async fn make_https_request(
-    host: &str,
-    ip: &SocketAddr,
-    body: &[u8],
-    extra: &Body,
-) -> anyhow::Result<Stats> {
-    let conn = native_tls::TlsConnector::new()?;
-    let connector = tokio_tls::TlsConnector::from(conn);
-    let start = std::time::Instant::now();
-    let stream = tokio::net::TcpStream::connect(ip).await?;
-    let connect = start.elapsed().as_millis();
-    let mut con = connector.connect(host, stream).await?;
-    let handshake = start.elapsed().as_millis() - connect;
-    con.write(body).await?;
-    match extra {
-        Body::File(head, middle, end) => {
-            con.write(
-                format!(
-                    "\r\ncontent-length: {}",
-                    &head.len() + &middle.len() + &end.len()
-                )
-                .as_bytes(),
-            )
-            .await?;
-            con.write(b"\r\n\r\n").await?;
-            con.write(&head).await?;
-            con.write(&middle).await?;
-            con.write_all(&end).await?;
-        }
-        Body::Simple(main) => {
-            con.write(format!("\r\ncontent-length: {}", &main.len()).as_bytes())
-                .await?;
-            con.write(b"\r\n\r\n").await?;
-            con.write_all(&main).await?;
+/// Open a connection to the endpoint. Returns the stream and whether it is over IPv6.
+pub async fn open(endpoint: &Endpoint) -> anyhow::Result<(Box<dyn Connection>, bool)> {
+    match endpoint {
+        Endpoint::Tcp(addrs) | Endpoint::Proxy { addrs, .. } => {
+            let stream = connect(addrs).await?;
+            let ipv6 = stream.peer_addr()?.is_ipv6();
+            Ok((Box::new(stream), ipv6))
         }
-        Body::None => {
-            con.write(b"\r\n\r\n").await?;
-            con.write_all(b" \r\n").await?;
+        #[cfg(unix)]
+        Endpoint::Unix(path) => {
+            let stream = tokio::net::UnixStream::connect(path).await?;
+            Ok((Box::new(stream), false))
         }
+        #[cfg(not(unix))]
+        Endpoint::Unix(_) => Err(anyhow::anyhow!(
+            "Unix domain sockets are not supported on this platform"
+        )),
     }
-    let writing = start.elapsed().as_millis() - handshake - connect;
-    let mut first: [u8; 1] = [0];
-    con.read(&mut first).await?;
-    let waiting = start.elapsed().as_millis() - handshake - connect - writing;
-    let mut v = Vec::new();
-    con.read_to_end(&mut v).await?;
-    let compelete = start.elapsed().as_millis();
-    return Ok(Stats {
-        connect: connect,
-        handshake: handshake,
-        waiting: waiting,
-        writing: writing,
-        read: compelete - connect - waiting - handshake - writing,
-        compelete: compelete,
-        length: v.len(),
-    });
 }
 
-pub async fn make_http_request(
-    ip: &SocketAddr,
-    body: &[u8],
-    extra: &Body,
-) -> anyhow::Result<Stats> {
-    let start = std::time::Instant::now();
-    let mut stream = tokio::net::TcpStream::connect(ip).await?;
-    let connect = start.elapsed().as_millis();
-    stream.write(body).await?;
-    match extra {
-        Body::File(head, middle, end) => {
-            stream
-                .write(
-                    format!(
-                        "\r\ncontent-length: {}",
-                        &head.len() + &middle.len() + &end.len()
-                    )
-                    .as_bytes(),
-                )
-                .await?;
-            stream.write(b"\r\n\r\n").await?;
-            stream.write(&head).await?;
-            stream.write(&middle).await?;
-            stream.write_all(&end).await?;
-            stream.write_all(b"\r\n").await?;
-        }
-        Body::Simple(main) => {
-            stream
-                .write(format!("\r\ncontent-length: {}", &main.len()).as_bytes())
-                .await?;
-            stream.write(b"\r\n\r\n").await?;
-            stream.write_all(&main).await?;
-        }
-        Body::None => {
-            stream.write(b"\r\n\r\n").await?;
-            stream.write_all(b" \r\n").await?;
-        }
+/// Open the tunnel to the host when the endpoint is a proxy. Plain http requests are sent to http
+/// proxies directly in absolute form, so they are only tunneled for tls.
+pub async fn tunnel<S: AsyncRead + AsyncWrite + Unpin>(
+    endpoint: &Endpoint,
+    stream: &mut S,
+    tls: bool,
+) -> anyhow::Result<()> {
+    match endpoint {
+        Endpoint::Proxy {
+            proxy, host, port, ..
+        } if tls || proxy.kind == ProxyKind::Socks5 => proxy.tunnel(stream, host, *port).await,
+        _ => Ok(()),
     }
-    let writing = start.elapsed().as_millis() - connect;
-    let mut first: [u8; 1] = [0];
-    stream.read(&mut first).await?;
-    let waiting = start.elapsed().as_millis() - connect - writing;
-    let mut v = Vec::new();
-    stream.read_to_end(&mut v).await?;
-    let compelete = start.elapsed().as_millis();
-    return Ok(Stats {
-        connect: connect,
-        handshake: 0,
-        waiting: waiting,
-        writing: writing,
-        read: compelete - connect - waiting - writing,
-        compelete: compelete,
-        length: v.len(),
-    });
 }
@@ -0,0 +1,41 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use futures::future::join_all;
+
+use crate::interrupt::{self, Interrupt};
+
+/// When a run stops, at whichever of the limits comes first.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    /// Connections made at the same time.
+    pub concurrency: u32,
+    /// Rounds of connections, none to keep going until the duration is over.
+    pub cycles: Option<u32>,
+    pub duration: Option<Duration>,
+}
+
+/// Start `concurrency` connections at a time with `start`, which is given the slot of each, and
+/// hand the outputs of every round to `done` until the limits are reached or the run is
+/// interrupted. Outputs are in the order the connections finished.
+pub async fn run<F, S, D>(limits: &Limits, interrupt: Option<&Interrupt>, mut start: S, mut done: D)
+where
+    F: Future,
+    S: FnMut(usize) -> F,
+    D: FnMut(Vec<F::Output>),
+{
+    let run = Instant::now();
+    let mut cycle = 0;
+    while limits.cycles.is_none_or(|c| cycle < c)
+        && limits.duration.is_none_or(|d| run.elapsed() < d)
+        && !interrupt.is_some_and(Interrupt::is_set)
+    {
+        cycle += 1;
+        let started: Vec<F> = (0..limits.concurrency as usize).map(&mut start).collect();
+        let outputs = match interrupt {
+            Some(interrupt) => interrupt::finish(started, interrupt).await,
+            None => join_all(started).await,
+        };
+        done(outputs);
+    }
+}
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::net::{TcpListener, TcpStream};
+use tokio::prelude::*;
+
+use crate::args_parser::Serve;
+
+/// Longest request head read, bigger ones are dropped.
+const MAX_HEAD: usize = 64 * 1024;
+/// Wait before accepting again after a failed accept, like when out of file descriptors.
+const ACCEPT_RETRY: Duration = Duration::from_millis(100);
+
+/// Read the head and the body of a request, the body is only read for its content-length.
+async fn read_request(stream: &mut TcpStream) -> anyhow::Result<()> {
+    let mut request = vec![];
+    let mut buf = [0; 4096];
+    let head_end = loop {
+        if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+        if request.len() > MAX_HEAD {
+            return Err(anyhow::anyhow!(
+                "Request head is larger than {} bytes",
+                MAX_HEAD
+            ));
+        }
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Err(anyhow::anyhow!(
+                "Connection closed before the request ended"
+            ));
+        }
+        request.extend_from_slice(&buf[..n]);
+    };
+    let head = String::from_utf8_lossy(&request[..head_end]).to_lowercase();
+    let length = head
+        .lines()
+        .filter_map(|line| line.strip_prefix("content-length:"))
+        .find_map(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut read = request.len() - head_end;
+    while read < length {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        read += n;
+    }
+    Ok(())
+}
+
+/// Answer every request with the same response, closing the connection after it.
+pub async fn serve(args: Serve) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(&args.listen).await?;
+    println!("Listening on http://{}", listener.local_addr()?);
+    serve_on(listener, args).await
+}
+
+/// Like `serve` on a listener that is already bound, `listen` of the args is not used.
+pub async fn serve_on(mut listener: TcpListener, args: Serve) -> anyhow::Result<()> {
+    let status = match http::StatusCode::from_u16(args.status) {
+        Ok(status) => status,
+        Err(_) => return Err(anyhow::anyhow!("Invalid status code {}", args.status)),
+    };
+    let response: Arc<Vec<u8>> = Arc::new(
+        format!(
+            "HTTP/1.1 {} {}\r\ncontent-type: text/plain\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            status.as_u16(),
+            status.canonical_reason().unwrap_or(""),
+            args.body.len(),
+            args.body
+        )
+        .into_bytes(),
+    );
+    let delay = Duration::from_millis(args.delay);
+    loop {
+        let mut stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                eprintln!("Failed to accept a connection: {}", err);
+                tokio::time::delay_for(ACCEPT_RETRY).await;
+                continue;
+            }
+        };
+        let response = response.clone();
+        tokio::spawn(async move {
+            if read_request(&mut stream).await.is_err() {
+                return;
+            }
+            tokio::time::delay_for(delay).await;
+            let _ = stream.write_all(&response).await;
+            let _ = stream.shutdown(std::net::Shutdown::Write);
+        });
+    }
+}
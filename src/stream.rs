@@ -0,0 +1,329 @@
+use std::time::{Duration, Instant};
+
+use tokio::prelude::*;
+
+use crate::dashboard::Progress;
+use crate::executor::Executor;
+use crate::http_parser;
+use crate::interrupt::Interrupt;
+use crate::request::Stats;
+use crate::schedule::{self, Limits};
+use crate::tables;
+
+/// Options of a streaming benchmark.
+#[derive(Debug)]
+pub struct Options {
+    pub limits: Limits,
+    /// How long each stream is read for.
+    pub hold: Duration,
+}
+
+/// One streamed response, times are in milliseconds.
+#[derive(Debug, Default)]
+pub struct Stream {
+    pub connect: u128,
+    pub proxy: u128,
+    pub handshake: u128,
+    /// From the request to the end of the response head.
+    pub waiting: u128,
+    /// From the request to the first event, none when no event came.
+    pub first_event: Option<u128>,
+    /// Time between each event and the one before it.
+    pub gaps: Vec<u128>,
+    pub events: usize,
+    /// Seconds the stream was read for.
+    pub seconds: f64,
+    /// Whether the server ended the response before the hold was over.
+    pub closed: bool,
+}
+
+impl Stream {
+    pub fn events_per_second(&self) -> f64 {
+        if self.seconds > 0.0 {
+            self.events as f64 / self.seconds
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Joins the chunks of a chunked body as they arrive.
+#[derive(Debug, Default)]
+pub struct Dechunker {
+    buf: Vec<u8>,
+    /// Bytes left in the current chunk.
+    left: usize,
+    /// Whether the line break after a chunk is still to be skipped.
+    crlf: bool,
+    pub done: bool,
+}
+
+impl Dechunker {
+    pub fn feed(&mut self, bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.buf.extend_from_slice(bytes);
+        let mut data = vec![];
+        while !self.done && !self.buf.is_empty() {
+            if self.left > 0 {
+                let n = self.left.min(self.buf.len());
+                data.extend(self.buf.drain(..n));
+                self.left -= n;
+                self.crlf = self.left == 0;
+            } else if self.crlf {
+                if self.buf.len() < 2 {
+                    break;
+                }
+                self.buf.drain(..2);
+                self.crlf = false;
+            } else {
+                let end = match self.buf.windows(2).position(|w| w == b"\r\n") {
+                    Some(end) => end,
+                    None => break,
+                };
+                let line = String::from_utf8_lossy(&self.buf[..end]).to_string();
+                let size = line.split(';').next().unwrap_or("").trim();
+                self.left = match usize::from_str_radix(size, 16) {
+                    Ok(size) => size,
+                    Err(_) => return Err(anyhow::anyhow!("Invalid chunked response")),
+                };
+                self.buf.drain(..end + 2);
+                self.done = self.left == 0;
+            }
+        }
+        Ok(data)
+    }
+}
+
+/// Counts the events of a body as it arrives. With `sse` an event is a block of fields ended by
+/// a blank line, comments like heartbeats are not counted, otherwise every line is an event.
+#[derive(Debug, Default)]
+pub struct Events {
+    sse: bool,
+    line: Vec<u8>,
+    /// Whether a field of an event was read since the last blank line.
+    pending: bool,
+}
+
+impl Events {
+    pub fn new(sse: bool) -> Events {
+        Events {
+            sse,
+            ..Events::default()
+        }
+    }
+
+    /// Number of events completed by the data.
+    pub fn feed(&mut self, data: &[u8]) -> usize {
+        let mut events = 0;
+        for &b in data {
+            if b != b'\n' {
+                self.line.push(b);
+                continue;
+            }
+            if self.line.last() == Some(&b'\r') {
+                self.line.pop();
+            }
+            if !self.sse {
+                if !self.line.is_empty() {
+                    events += 1;
+                }
+            } else if self.line.is_empty() {
+                if self.pending {
+                    events += 1;
+                }
+                self.pending = false;
+            } else if self.line[0] != b':' {
+                self.pending = true;
+            }
+            self.line.clear();
+        }
+        events
+    }
+}
+
+/// Send the request and read the response for the hold duration, noting when each event came.
+async fn read<E: Executor>(
+    executor: &E,
+    request: &[u8],
+    options: &Options,
+    interrupt: &Interrupt,
+) -> anyhow::Result<Stream>
+where
+    E::Connection: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut stats = Stats::default();
+    let mut con = executor.connect(&mut stats).await?;
+    executor.send(&mut con, request, &mut stats).await?;
+    let start = Instant::now();
+    let mut stream = Stream {
+        connect: stats.connect,
+        proxy: stats.proxy,
+        handshake: stats.handshake,
+        ..Stream::default()
+    };
+    let mut buf = vec![];
+    let mut chunk = [0; 8192];
+    let mut dechunker: Option<Dechunker> = None;
+    let mut events = Events::default();
+    let mut head_read = false;
+    let mut last = None;
+    while !interrupt.is_set() {
+        let wait = match options.hold.checked_sub(start.elapsed()) {
+            Some(wait) => wait,
+            None => break,
+        };
+        let n = tokio::select! {
+            read = tokio::time::timeout(wait, con.read(&mut chunk)) => match read {
+                Ok(read) => read?,
+                Err(_) => continue,
+            },
+            _ = interrupt.wait() => break,
+        };
+        if n == 0 {
+            stream.closed = true;
+            break;
+        }
+        let data = if head_read {
+            chunk[..n].to_vec()
+        } else {
+            buf.extend_from_slice(&chunk[..n]);
+            let end = match buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                Some(end) => end + 4,
+                None => continue,
+            };
+            let head = match http_parser::parse_response_head(&buf[..end]) {
+                Some(head) => head,
+                None => return Err(anyhow::anyhow!("Invalid response from server")),
+            };
+            if !(200..300).contains(&head.status) {
+                return Err(anyhow::anyhow!(
+                    "Stream refused with status {}",
+                    head.status
+                ));
+            }
+            stream.waiting = start.elapsed().as_millis();
+            head_read = true;
+            events = Events::new(
+                head.header("content-type")
+                    .is_some_and(|t| t.trim().starts_with("text/event-stream")),
+            );
+            if head
+                .all("transfer-encoding")
+                .any(|te| te.to_lowercase().contains("chunked"))
+            {
+                dechunker = Some(Dechunker::default());
+            }
+            buf.split_off(end)
+        };
+        let data = match &mut dechunker {
+            Some(dechunker) => dechunker.feed(&data)?,
+            None => data,
+        };
+        let count = events.feed(&data);
+        if count > 0 {
+            let now = start.elapsed().as_millis();
+            stream.first_event.get_or_insert(now);
+            if let Some(last) = last {
+                stream.gaps.push(now - last);
+            }
+            // events that came in the same read are apart by no time
+            stream.gaps.extend(std::iter::repeat_n(0, count - 1));
+            stream.events += count;
+            last = Some(now);
+        }
+        if dechunker.as_ref().is_some_and(|d| d.done) {
+            stream.closed = true;
+            break;
+        }
+    }
+    if !head_read {
+        return Err(anyhow::anyhow!("No response head before the hold was over"));
+    }
+    stream.seconds = start.elapsed().as_secs_f64();
+    executor.close(con).await;
+    Ok(stream)
+}
+
+/// Keep `concurrency` streams open at a time until the limits are reached or the run is
+/// interrupted. Results are in the order the streams finished.
+pub async fn benchmark<E: Executor>(
+    executor: &E,
+    request: &[u8],
+    options: &Options,
+    interrupt: &Interrupt,
+    progress: &Progress,
+) -> Vec<anyhow::Result<Stream>>
+where
+    E::Connection: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut streams = vec![];
+    schedule::run(
+        &options.limits,
+        Some(interrupt),
+        |_| async {
+            let result = read(executor, request, options, interrupt).await;
+            progress.record(result.as_ref().ok().map(|s| s.waiting));
+            result
+        },
+        |done| streams.extend(done),
+    )
+    .await;
+    streams
+}
+
+/// Print the report of the streams.
+pub fn print(streams: &[anyhow::Result<Stream>], run_time: f64, tls: bool) {
+    let opened: Vec<&Stream> = streams.iter().filter_map(|s| s.as_ref().ok()).collect();
+    let events: usize = opened.iter().map(|s| s.events).sum();
+    let mut first: Vec<u128> = opened.iter().filter_map(|s| s.first_event).collect();
+    first.sort_unstable();
+    let rates: Vec<f64> = opened.iter().map(|s| s.events_per_second()).collect();
+    println!(" Number of Streams           : {}", streams.len());
+    println!(" Number of Opened            : {}", opened.len());
+    println!(
+        " Number of Failed            : {}",
+        streams.len() - opened.len()
+    );
+    if let Some(Err(err)) = streams.iter().find(|s| s.is_err()) {
+        println!(" First Error                 : {}", err);
+    }
+    println!(
+        " Closed by the Server        : {}",
+        opened.iter().filter(|s| s.closed).count()
+    );
+    println!(" Events Received             : {}", events);
+    println!(
+        " Events per second           : {:.2}",
+        events as f64 / run_time
+    );
+    if !rates.is_empty() {
+        println!(
+            " Events/s of a Stream        : {:.2} min, {:.2} avg, {:.2} max",
+            rates.iter().cloned().fold(f64::INFINITY, f64::min),
+            rates.iter().sum::<f64>() / rates.len() as f64,
+            rates.iter().cloned().fold(0.0, f64::max)
+        );
+    }
+    let column =
+        |phase: fn(&Stream) -> u128| opened.iter().map(|s| phase(s)).collect::<Vec<u128>>();
+    let mut rows = vec![("Connection Time", column(|s| s.connect))];
+    if opened.iter().any(|s| s.proxy > 0) {
+        rows.push(("Proxy Tunnel Setup", column(|s| s.proxy)));
+    }
+    if tls {
+        rows.push(("Tls Handshake Time", column(|s| s.handshake)));
+    }
+    rows.push(("Response Head", column(|s| s.waiting)));
+    rows.push(("First Event", first.clone()));
+    rows.push((
+        "Between Events",
+        opened.iter().flat_map(|s| s.gaps.iter().copied()).collect(),
+    ));
+    println!();
+    tables::create_phase_table(&rows);
+    if first.is_empty() {
+        println!("\nNo event was received");
+    } else {
+        println!("\nApprox time Required for the first event of % of streams");
+        tables::create_percent_table(&first);
+    }
+}
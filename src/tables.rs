@@ -1,7 +1,32 @@
+use crate::calculate;
+use crate::compare::Delta;
+use crate::redirect::Hop;
+use crate::report::Report;
 use crate::request::Stats;
+use crate::threshold::{Measured, Metric, Threshold};
+use crate::timeline::Bucket;
+use crate::tls::TlsInfo;
 use prettytable::{Cell, Row, Table};
 
-pub fn create_task_table(min: &Stats, max: &Stats, ac: &Stats, lookup_time: u128) {
+/// Phases that only have a row in the task table when they are used.
+#[derive(Debug, Default)]
+pub struct OptionalPhases {
+    pub proxy: bool,
+    pub digest: bool,
+    pub decode: bool,
+}
+
+/// Create the table of time taken by each phase. When tls resumption is enabled the handshakes are
+/// given as (full, resumed) and reported in separate rows.
+pub fn create_task_table(
+    min: &Stats,
+    max: &Stats,
+    ac: &Stats,
+    sd: &Stats,
+    lookup_time: u128,
+    handshakes: Option<(&[u128], &[u128])>,
+    phases: &OptionalPhases,
+) {
     let mut table = Table::new();
 
     table.add_row(Row::new(vec![
@@ -9,43 +34,137 @@ pub fn create_task_table(min: &Stats, max: &Stats, ac: &Stats, lookup_time: u128
         Cell::new("Min Time(milliseconds)"),
         Cell::new("Average Time(milliseconds)"),
         Cell::new("Max Time(milliseconds)"),
+        Cell::new("Std Deviation(milliseconds)"),
     ]));
+    let row = |name: &str, min: u128, avg: u128, max: u128, sd: u128| {
+        Row::new(vec![
+            Cell::new(name),
+            Cell::new(&min.to_string()),
+            Cell::new(&avg.to_string()),
+            Cell::new(&max.to_string()),
+            Cell::new(&sd.to_string()),
+        ])
+    };
+    table.add_row(row("Dns Query", lookup_time, lookup_time, lookup_time, 0));
+    if phases.digest {
+        table.add_row(row(
+            "Digest Challenge",
+            min.auth,
+            ac.auth,
+            max.auth,
+            sd.auth,
+        ));
+    }
+    table.add_row(row(
+        "Connection Time",
+        min.connect,
+        ac.connect,
+        max.connect,
+        sd.connect,
+    ));
+    if phases.proxy {
+        table.add_row(row(
+            "Proxy Tunnel Setup",
+            min.proxy,
+            ac.proxy,
+            max.proxy,
+            sd.proxy,
+        ));
+    }
+    match handshakes {
+        Some((full, resumed)) => {
+            for (name, values) in [("full", full), ("resumed", resumed)].iter() {
+                let (min, avg, max) = calculate::min_avg_max(values);
+                table.add_row(row(
+                    &format!("Tls Handshake Time ({} x{})", name, values.len()),
+                    min,
+                    avg,
+                    max,
+                    calculate::std_dev_of(values),
+                ));
+            }
+        }
+        None => {
+            table.add_row(row(
+                "Tls Handshake Time",
+                min.handshake,
+                ac.handshake,
+                max.handshake,
+                sd.handshake,
+            ));
+        }
+    }
+    table.add_row(row(
+        "Waiting For Response",
+        min.waiting,
+        ac.waiting,
+        max.waiting,
+        sd.waiting,
+    ));
+    table.add_row(row(
+        "Writing the Request",
+        min.writing,
+        ac.writing,
+        max.writing,
+        sd.writing,
+    ));
+    if phases.decode {
+        table.add_row(row(
+            "Decoding the Response",
+            min.decode,
+            ac.decode,
+            max.decode,
+            sd.decode,
+        ));
+    }
+    table.add_row(row(
+        "Compelete",
+        min.compelete,
+        ac.compelete,
+        max.compelete,
+        sd.compelete,
+    ));
+    table.printstd();
+}
+
+/// Create the table of bytes in each request and response. The sizes in `ac` are totals of the run,
+/// the decoded body is only shown when the responses were decoded.
+pub fn create_size_table(min: &Stats, max: &Stats, ac: &Stats, requests: usize, decoded: bool) {
+    let mut table = Table::new();
     table.add_row(Row::new(vec![
-        Cell::new("Dns Query"),
-        Cell::new(&lookup_time.to_string()),
-        Cell::new(&lookup_time.to_string()),
-        Cell::new(&lookup_time.to_string()),
-    ]));
-    table.add_row(Row::new(vec![
-        Cell::new("Connection Time"),
-        Cell::new(&min.connect.to_string()),
-        Cell::new(&ac.connect.to_string()),
-        Cell::new(&max.connect.to_string()),
-    ]));
-    table.add_row(Row::new(vec![
-        Cell::new("Tls Handshake Time"),
-        Cell::new(&min.handshake.to_string()),
-        Cell::new(&ac.handshake.to_string()),
-        Cell::new(&max.handshake.to_string()),
-    ]));
-    table.add_row(Row::new(vec![
-        Cell::new("Waiting For Response"),
-        Cell::new(&min.waiting.to_string()),
-        Cell::new(&ac.waiting.to_string()),
-        Cell::new(&max.waiting.to_string()),
-    ]));
-    table.add_row(Row::new(vec![
-        Cell::new("Writing the Request"),
-        Cell::new(&min.writing.to_string()),
-        Cell::new(&ac.writing.to_string()),
-        Cell::new(&max.writing.to_string()),
-    ]));
-    table.add_row(Row::new(vec![
-        Cell::new("Compelete"),
-        Cell::new(&min.compelete.to_string()),
-        Cell::new(&ac.compelete.to_string()),
-        Cell::new(&max.compelete.to_string()),
+        Cell::new("Size"),
+        Cell::new("Min(bytes)"),
+        Cell::new("Average(bytes)"),
+        Cell::new("Max(bytes)"),
+        Cell::new("Total(bytes)"),
     ]));
+    let mut rows = vec![
+        ("Request Sent", min.sent, ac.sent, max.sent),
+        (
+            "Response Headers",
+            min.header_length,
+            ac.header_length,
+            max.header_length,
+        ),
+        (
+            "Response Body",
+            min.body_length,
+            ac.body_length,
+            max.body_length,
+        ),
+    ];
+    if decoded {
+        rows.push(("Decoded Body", min.decoded, ac.decoded, max.decoded));
+    }
+    for (name, min, total, max) in rows.iter() {
+        table.add_row(Row::new(vec![
+            Cell::new(name),
+            Cell::new(&min.to_string()),
+            Cell::new(&(total / requests.max(1)).to_string()),
+            Cell::new(&max.to_string()),
+            Cell::new(&total.to_string()),
+        ]));
+    }
     table.printstd();
 }
 
@@ -78,3 +197,201 @@ pub fn create_percent_table(compeleted: &[u128]) {
     ]));
     table.printstd();
 }
+
+/// Create the table of redirects, the nth row is the nth redirect of every request that had one.
+pub fn create_redirect_table(chains: &[Vec<Hop>]) {
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Redirect"),
+        Cell::new("Location"),
+        Cell::new("Requests"),
+        Cell::new("Min Time(milliseconds)"),
+        Cell::new("Average Time(milliseconds)"),
+        Cell::new("Max Time(milliseconds)"),
+    ]));
+    let longest = chains.iter().map(|c| c.len()).max().unwrap_or(0);
+    for n in 0..longest {
+        let hops: Vec<&Hop> = chains.iter().filter_map(|c| c.get(n)).collect();
+        let times: Vec<u128> = hops.iter().map(|hop| hop.time).collect();
+        let (min, avg, max) = calculate::min_avg_max(&times);
+        table.add_row(Row::new(vec![
+            Cell::new(&(n + 1).to_string()),
+            Cell::new(&hops[0].url),
+            Cell::new(&hops.len().to_string()),
+            Cell::new(&min.to_string()),
+            Cell::new(&avg.to_string()),
+            Cell::new(&max.to_string()),
+        ]));
+    }
+    table.printstd();
+}
+
+pub fn create_timeline_table(buckets: &[Bucket]) {
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Time (s)"),
+        Cell::new("Requests"),
+        Cell::new("Requests/s"),
+        Cell::new("Error Rate"),
+        Cell::new("50%(milliseconds)"),
+        Cell::new("99%(milliseconds)"),
+    ]));
+    for b in buckets {
+        table.add_row(Row::new(vec![
+            Cell::new(&format!("{:.2}", b.start)),
+            Cell::new(&b.requests.to_string()),
+            Cell::new(&format!("{:.2}", b.rps)),
+            Cell::new(&format!("{:.2}%", b.error_rate)),
+            Cell::new(&b.p50.to_string()),
+            Cell::new(&b.p99.to_string()),
+        ]));
+    }
+    table.printstd();
+}
+
+/// Create the table of the phases of websocket connections and streams, one row per phase.
+pub fn create_phase_table(rows: &[(&str, Vec<u128>)]) {
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Phase"),
+        Cell::new("Count"),
+        Cell::new("Min Time(milliseconds)"),
+        Cell::new("Average Time(milliseconds)"),
+        Cell::new("Max Time(milliseconds)"),
+        Cell::new("Std Dev(milliseconds)"),
+    ]));
+    for (name, times) in rows {
+        let (min, avg, max) = calculate::min_avg_max(times);
+        table.add_row(Row::new(vec![
+            Cell::new(name),
+            Cell::new(&times.len().to_string()),
+            Cell::new(&min.to_string()),
+            Cell::new(&avg.to_string()),
+            Cell::new(&max.to_string()),
+            Cell::new(&calculate::std_dev_of(times).to_string()),
+        ]));
+    }
+    table.printstd();
+}
+
+/// Create the table of request counts and rates of the two compared runs.
+pub fn create_summary_compare_table(before: &Report, after: &Report) {
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Run"),
+        Cell::new("Before"),
+        Cell::new("After"),
+    ]));
+    let rows = [
+        (
+            "Requests",
+            (before.success + before.failed).to_string(),
+            (after.success + after.failed).to_string(),
+        ),
+        (
+            "Requests per second",
+            format!("{:.2}", before.requests_per_second()),
+            format!("{:.2}", after.requests_per_second()),
+        ),
+        (
+            "Error Rate",
+            format!("{:.2}%", before.error_rate()),
+            format!("{:.2}%", after.error_rate()),
+        ),
+    ];
+    for (name, before, after) in rows.iter() {
+        table.add_row(Row::new(vec![
+            Cell::new(name),
+            Cell::new(before),
+            Cell::new(after),
+        ]));
+    }
+    table.printstd();
+}
+
+/// Create the table of differences between two runs, `-` is shown for what could not be computed.
+pub fn create_compare_table(name: &str, deltas: &[Delta]) {
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new(name),
+        Cell::new("Before(milliseconds)"),
+        Cell::new("After(milliseconds)"),
+        Cell::new("Change"),
+        Cell::new("P Value"),
+        Cell::new("Significant"),
+    ]));
+    for d in deltas {
+        table.add_row(Row::new(vec![
+            Cell::new(&d.name),
+            Cell::new(&format!("{:.2}", d.before)),
+            Cell::new(&format!("{:.2}", d.after)),
+            Cell::new(&d.change().map_or("-".to_owned(), |c| format!("{:+.2}%", c))),
+            Cell::new(&d.p_value.map_or("-".to_owned(), |p| format!("{:.4}", p))),
+            Cell::new(match (d.p_value, d.significant()) {
+                (None, _) => "-",
+                (Some(_), true) => "yes",
+                (Some(_), false) => "no",
+            }),
+        ]));
+    }
+    table.printstd();
+}
+
+/// Create the table of the thresholds given with `--fail-if` and the measured values.
+pub fn create_threshold_table(thresholds: &[Threshold], measured: &Measured) {
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Threshold"),
+        Cell::new("Measured"),
+        Cell::new("Result"),
+    ]));
+    for threshold in thresholds {
+        let value = match (threshold.metric, measured.get(threshold.metric)) {
+            (_, None) => "-".to_owned(),
+            (Metric::ErrorRate, Some(v)) => format!("{:.2}%", v),
+            (Metric::Rps, Some(v)) => format!("{:.2}", v),
+            (_, Some(v)) => format!("{}ms", v),
+        };
+        table.add_row(Row::new(vec![
+            Cell::new(&threshold.to_string()),
+            Cell::new(&value),
+            Cell::new(if threshold.failed(measured) {
+                "failed"
+            } else {
+                "passed"
+            }),
+        ]));
+    }
+    table.printstd();
+}
+
+pub fn create_tls_table(info: &TlsInfo) {
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Protocol"),
+        Cell::new(&info.version),
+    ]));
+    table.add_row(Row::new(vec![Cell::new("Cipher"), Cell::new(&info.cipher)]));
+    table.add_row(Row::new(vec![
+        Cell::new("ALPN"),
+        Cell::new(info.alpn.as_deref().unwrap_or("none")),
+    ]));
+    table.printstd();
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Certificate"),
+        Cell::new("Issuer"),
+        Cell::new("Expires"),
+        Cell::new("Days Left"),
+    ]));
+    for cert in &info.chain {
+        table.add_row(Row::new(vec![
+            Cell::new(&cert.subject),
+            Cell::new(&cert.issuer),
+            Cell::new(&cert.not_after),
+            Cell::new(&cert.days_left.to_string()),
+        ]));
+    }
+    table.printstd();
+}
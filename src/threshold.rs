@@ -0,0 +1,139 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::compare;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    /// Percent of the requests compeleted within the time, like `p95`.
+    Percentile(usize),
+    Average,
+    Max,
+    ErrorRate,
+    Rps,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+}
+
+/// Condition failing the run when true, in form of `p95>200ms`, `error_rate>1%` or `rps<1000`.
+/// Times are in milliseconds unless they end with `s`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Threshold {
+    pub metric: Metric,
+    pub op: Op,
+    pub value: f64,
+    text: String,
+}
+
+impl FromStr for Threshold {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let at = match s.find(['<', '>']) {
+            Some(at) => at,
+            None => {
+                return Err(format!(
+                    "{} should be in form of metric>value or metric<value",
+                    s
+                ))
+            }
+        };
+        let (op, rest) = match (&s[at..at + 1], s[at + 1..].strip_prefix('=')) {
+            (">", Some(rest)) => (Op::GreaterOrEqual, rest),
+            (">", None) => (Op::Greater, &s[at + 1..]),
+            (_, Some(rest)) => (Op::LessOrEqual, rest),
+            (_, None) => (Op::Less, &s[at + 1..]),
+        };
+        let name = s[..at].trim().to_lowercase();
+        let metric = match name.as_str() {
+            "avg" => Metric::Average,
+            "max" => Metric::Max,
+            "error_rate" => Metric::ErrorRate,
+            "rps" => Metric::Rps,
+            _ => match name.strip_prefix('p').map(|p| p.parse::<usize>()) {
+                Some(Ok(p)) if p <= 100 => Metric::Percentile(p),
+                _ => {
+                    return Err(format!(
+                        "unknown metric {}, expected pNN, avg, max, error_rate or rps",
+                        name
+                    ))
+                }
+            },
+        };
+        let rest = rest.trim().to_lowercase();
+        let (number, scale) = match metric {
+            Metric::ErrorRate => (rest.strip_suffix('%').unwrap_or(&rest), 1.0),
+            Metric::Rps => (rest.as_str(), 1.0),
+            _ => match (rest.strip_suffix("ms"), rest.strip_suffix('s')) {
+                (Some(ms), _) => (ms, 1.0),
+                (None, Some(s)) => (s, 1000.0),
+                (None, None) => (rest.as_str(), 1.0),
+            },
+        };
+        let value = match number.trim().parse::<f64>() {
+            Ok(value) if value.is_finite() => value * scale,
+            _ => return Err(format!("invalid value {} in {}", rest, s)),
+        };
+        Ok(Threshold {
+            metric,
+            op,
+            value,
+            text: s.trim().to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for Threshold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Results of the run the thresholds are checked against.
+#[derive(Debug, Default)]
+pub struct Measured<'a> {
+    /// Sorted time taken by the successful requests.
+    pub compeleted: &'a [u128],
+    pub error_rate: f64,
+    pub rps: f64,
+}
+
+impl Measured<'_> {
+    /// Value of the metric, none for times when no request was successful.
+    pub fn get(&self, metric: Metric) -> Option<f64> {
+        let times = self.compeleted;
+        match metric {
+            Metric::ErrorRate => return Some(self.error_rate),
+            Metric::Rps => return Some(self.rps),
+            _ if times.is_empty() => return None,
+            _ => {}
+        }
+        let time = match metric {
+            Metric::Percentile(p) => compare::percentile(times, p),
+            Metric::Average => times.iter().sum::<u128>() / times.len() as u128,
+            _ => times[times.len() - 1],
+        };
+        Some(time as f64)
+    }
+}
+
+impl Threshold {
+    /// Whether the run failed this threshold, a time that could not be measured fails it.
+    pub fn failed(&self, measured: &Measured) -> bool {
+        match measured.get(self.metric) {
+            Some(v) => match self.op {
+                Op::Greater => v > self.value,
+                Op::GreaterOrEqual => v >= self.value,
+                Op::Less => v < self.value,
+                Op::LessOrEqual => v <= self.value,
+            },
+            None => true,
+        }
+    }
+}
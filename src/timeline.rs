@@ -0,0 +1,73 @@
+use std::path::Path;
+use std::time::Duration;
+
+use crate::compare;
+
+/// A finished request, `latency` is none when it failed.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    /// Time since the start of the run when the request finished.
+    pub at: Duration,
+    pub latency: Option<u128>,
+}
+
+/// Requests that finished in one interval of the run.
+#[derive(Debug, PartialEq)]
+pub struct Bucket {
+    /// Start of the interval in seconds since the start of the run.
+    pub start: f64,
+    pub requests: usize,
+    pub failed: usize,
+    pub rps: f64,
+    /// Percentage of the requests that failed.
+    pub error_rate: f64,
+    pub p50: u128,
+    pub p99: u128,
+}
+
+/// Group the samples by the interval they finished in. Intervals without any request are kept so
+/// stalls show up in the timeline.
+pub fn buckets(samples: &[Sample], interval: Duration) -> Vec<Bucket> {
+    let interval = interval.as_secs_f64();
+    let index = |sample: &Sample| (sample.at.as_secs_f64() / interval) as usize;
+    let count = samples.iter().map(index).max().map(|i| i + 1).unwrap_or(0);
+    let mut grouped = vec![vec![]; count];
+    for sample in samples {
+        grouped[index(sample)].push(sample);
+    }
+    grouped
+        .iter()
+        .enumerate()
+        .map(|(i, group)| {
+            let mut latencies: Vec<u128> = group.iter().filter_map(|s| s.latency).collect();
+            latencies.sort_unstable();
+            let percentile = |p| compare::percentile(&latencies, p);
+            let failed = group.len() - latencies.len();
+            Bucket {
+                start: i as f64 * interval,
+                requests: group.len(),
+                failed,
+                rps: latencies.len() as f64 / interval,
+                error_rate: match group.len() {
+                    0 => 0.0,
+                    n => failed as f64 * 100.0 / n as f64,
+                },
+                p50: percentile(50),
+                p99: percentile(99),
+            }
+        })
+        .collect()
+}
+
+/// Write the timeline as csv.
+pub async fn write_csv(path: &Path, buckets: &[Bucket]) -> anyhow::Result<()> {
+    let mut csv = String::from("start_seconds,requests,failed,rps,error_rate,p50_ms,p99_ms\n");
+    for b in buckets {
+        csv.push_str(&format!(
+            "{:.3},{},{},{:.2},{:.2},{},{}\n",
+            b.start, b.requests, b.failed, b.rps, b.error_rate, b.p50, b.p99
+        ));
+    }
+    tokio::fs::write(path, csv).await?;
+    Ok(())
+}
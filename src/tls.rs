@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use openssl::asn1::Asn1Time;
+use openssl::ex_data::Index;
+use openssl::pkcs12::Pkcs12;
+use openssl::ssl::{
+    Ssl, SslConnector, SslFiletype, SslMethod, SslSession, SslSessionCacheMode, SslVerifyMode,
+};
+use openssl::x509::{X509NameRef, X509};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::prelude::*;
+use tokio_openssl::SslStream;
+
+use crate::request::{self, Endpoint};
+
+/// Tls settings for https requests. The connector is built once and shared by all the requests.
+#[derive(Debug, Default)]
+pub struct TlsConfig {
+    /// PEM bundle of extra certificate authorities to trust.
+    pub cacert: Option<PathBuf>,
+    /// Skip verification of the certificate and hostname.
+    pub insecure: bool,
+    /// Client certificate, PEM when `key` is given otherwise PKCS#12.
+    pub cert: Option<PathBuf>,
+    /// PEM private key for the client certificate.
+    pub key: Option<PathBuf>,
+    /// Password of the PKCS#12 client certificate.
+    pub cert_password: Option<String>,
+    /// Server name sent in SNI and checked against the certificate instead of the url host.
+    pub sni: Option<String>,
+    /// Resume the sessions of earlier requests instead of making a full handshake.
+    pub resume: bool,
+}
+
+/// Sessions of earlier handshakes by server name. The name is attached to each connection as ex data.
+struct SessionCache {
+    index: Index<Ssl, String>,
+    sessions: Mutex<HashMap<String, SslSession>>,
+}
+
+/// Tls connector shared by all the https requests.
+#[derive(Clone)]
+pub struct Connector {
+    inner: SslConnector,
+    verify_hostname: bool,
+    cache: Option<Arc<SessionCache>>,
+}
+
+/// Summary of a certificate presented by the server.
+#[derive(Debug)]
+pub struct CertSummary {
+    pub subject: String,
+    pub issuer: String,
+    pub not_after: String,
+    /// Days until the certificate expires, negative when it has expired.
+    pub days_left: i32,
+}
+
+/// Details negotiated in the handshake with the server.
+#[derive(Debug)]
+pub struct TlsInfo {
+    pub version: String,
+    pub cipher: String,
+    pub alpn: Option<String>,
+    pub chain: Vec<CertSummary>,
+}
+
+/// Format a name as comma separated `key=value` entries eg. `CN=google.com, O=Google LLC`.
+pub fn name_to_string(name: &X509NameRef) -> String {
+    name.entries()
+        .map(|entry| {
+            let key = entry.object().nid().short_name().unwrap_or("?");
+            format!(
+                "{}={}",
+                key,
+                String::from_utf8_lossy(entry.data().as_slice())
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Split a PEM bundle into the certificates it contains.
+pub fn split_pem_bundle(bundle: &[u8]) -> Vec<&[u8]> {
+    const END: &[u8] = b"-----END CERTIFICATE-----";
+    let mut certs = vec![];
+    let mut rest = bundle;
+    while let Some(pos) = rest.windows(END.len()).position(|w| w == END) {
+        certs.push(&rest[..pos + END.len()]);
+        rest = &rest[pos + END.len()..];
+    }
+    certs
+}
+
+impl TlsConfig {
+    pub fn server_name<'a>(&'a self, host: &'a str) -> &'a str {
+        match &self.sni {
+            Some(sni) => sni,
+            None => host.trim_start_matches('[').trim_end_matches(']'),
+        }
+    }
+
+    pub async fn connector(&self) -> anyhow::Result<Connector> {
+        let mut builder = SslConnector::builder(SslMethod::tls())?;
+        if let Some(path) = &self.cacert {
+            let bundle = tokio::fs::read(path).await?;
+            let certs = split_pem_bundle(&bundle);
+            if certs.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "No certificate found in {}",
+                    path.display()
+                ));
+            }
+            for cert in certs {
+                builder.cert_store_mut().add_cert(X509::from_pem(cert)?)?;
+            }
+        }
+        if self.insecure {
+            builder.set_verify(SslVerifyMode::NONE);
+        }
+        builder.set_alpn_protos(b"\x08http/1.1")?;
+        match (&self.cert, &self.key) {
+            (Some(cert), Some(key)) => {
+                builder.set_certificate_chain_file(cert)?;
+                builder.set_private_key_file(key, SslFiletype::PEM)?;
+                builder.check_private_key()?;
+            }
+            (Some(cert), None) => {
+                let der = tokio::fs::read(cert).await?;
+                let password = self.cert_password.as_deref().unwrap_or("");
+                let identity = Pkcs12::from_der(&der)?.parse2(password)?;
+                match (identity.cert, identity.pkey) {
+                    (Some(cert), Some(pkey)) => {
+                        builder.set_certificate(&cert)?;
+                        builder.set_private_key(&pkey)?;
+                    }
+                    _ => {
+                        return Err(anyhow::anyhow!(
+                            "PKCS#12 file does not contain a certificate and key"
+                        ))
+                    }
+                }
+                if let Some(chain) = identity.ca {
+                    for cert in chain {
+                        builder.add_extra_chain_cert(cert)?;
+                    }
+                }
+            }
+            (None, Some(_)) => {
+                return Err(anyhow::anyhow!("--key requires a certificate with --cert"))
+            }
+            (None, None) => {}
+        }
+        let cache = if self.resume {
+            let cache = Arc::new(SessionCache {
+                index: Ssl::new_ex_index()?,
+                sessions: Mutex::new(HashMap::new()),
+            });
+            let store = cache.clone();
+            builder.set_session_cache_mode(SslSessionCacheMode::CLIENT);
+            builder.set_new_session_callback(move |ssl, session| {
+                if let Some(name) = ssl.ex_data(store.index) {
+                    store.sessions.lock().unwrap().insert(name.clone(), session);
+                }
+            });
+            Some(cache)
+        } else {
+            None
+        };
+        Ok(Connector {
+            inner: builder.build(),
+            verify_hostname: !self.insecure,
+            cache,
+        })
+    }
+}
+
+impl Connector {
+    /// Make the tls handshake over stream, resuming the last session with the server when enabled.
+    pub async fn connect<S>(&self, server_name: &str, stream: S) -> anyhow::Result<SslStream<S>>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Debug,
+    {
+        let mut config = self.inner.configure()?;
+        config.set_verify_hostname(self.verify_hostname);
+        if let Some(cache) = &self.cache {
+            config.set_ex_data(cache.index, server_name.to_owned());
+            if let Some(session) = cache.sessions.lock().unwrap().get(server_name) {
+                // Safe as the cache only holds sessions created by the context of this connector.
+                unsafe { config.set_session(session)? };
+            }
+        }
+        tokio_openssl::connect(config, server_name, stream)
+            .await
+            .map_err(|err| anyhow::anyhow!("Tls handshake failed: {}", err))
+    }
+}
+
+/// Make a handshake with the server and collect the negotiated details and certificate chain.
+pub async fn inspect(
+    connector: &Connector,
+    server_name: &str,
+    endpoint: &Endpoint,
+) -> anyhow::Result<TlsInfo> {
+    let (mut stream, _) = request::open(endpoint).await?;
+    request::tunnel(endpoint, &mut stream, true).await?;
+    let mut con = connector.connect(server_name, stream).await?;
+    let ssl = con.ssl();
+    let now = Asn1Time::days_from_now(0)?;
+    let mut chain = vec![];
+    if let Some(certs) = ssl.peer_cert_chain() {
+        for cert in certs {
+            chain.push(CertSummary {
+                subject: name_to_string(cert.subject_name()),
+                issuer: name_to_string(cert.issuer_name()),
+                not_after: cert.not_after().to_string(),
+                days_left: now.diff(cert.not_after())?.days,
+            });
+        }
+    }
+    let info = TlsInfo {
+        version: ssl.version_str().to_owned(),
+        cipher: match ssl.current_cipher() {
+            Some(cipher) => cipher.name().to_owned(),
+            None => "unknown".to_owned(),
+        },
+        alpn: ssl
+            .selected_alpn_protocol()
+            .map(|p| String::from_utf8_lossy(p).into_owned()),
+        chain,
+    };
+    let _ = con.shutdown().await;
+    Ok(info)
+}
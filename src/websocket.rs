@@ -0,0 +1,364 @@
+// https://tools.ietf.org/html/rfc6455
+use std::convert::TryFrom;
+use std::time::{Duration, Instant};
+
+use tokio::prelude::*;
+
+use crate::dashboard::Progress;
+use crate::executor::Executor;
+use crate::http_parser;
+use crate::interrupt::Interrupt;
+use crate::request::Stats;
+use crate::schedule::{self, Limits};
+use crate::tables;
+
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const CONTINUATION: u8 = 0x0;
+const TEXT: u8 = 0x1;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xA;
+/// Largest message read from the server, bigger ones fail the connection.
+const MAX_MESSAGE: usize = 16 * 1024 * 1024;
+
+/// Options of a websocket benchmark.
+#[derive(Debug)]
+pub struct Options {
+    pub limits: Limits,
+    /// Sent as a text message and expected back by the server.
+    pub payload: Vec<u8>,
+    /// Messages sent on each connection.
+    pub messages: u32,
+    /// Messages sent per second on each connection, as fast as the echoes come back when none.
+    pub rate: Option<f64>,
+    /// How long each connection is kept open after its messages.
+    pub hold: Duration,
+}
+
+/// One websocket connection, times are in milliseconds.
+#[derive(Debug, Default)]
+pub struct Session {
+    pub connect: u128,
+    pub proxy: u128,
+    pub handshake: u128,
+    /// From the upgrade request to the 101 response.
+    pub upgrade: u128,
+    /// Round trip of each message.
+    pub latencies: Vec<u128>,
+    /// Whether the connection still answered a ping after being held.
+    pub held: bool,
+}
+
+/// Value of `Sec-WebSocket-Accept` the server should answer the key with.
+pub fn accept_key(key: &str) -> String {
+    base64::encode(openssl::sha::sha1(format!("{}{}", key, GUID).as_bytes()))
+}
+
+fn random<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    // the key and the masks only need to be unpredictable, not secret
+    if openssl::rand::rand_bytes(&mut bytes).is_err() {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = (nanos >> (i % 4 * 8)) as u8;
+        }
+    }
+    bytes
+}
+
+/// Frame with the payload masked as clients have to.
+pub fn encode_frame(opcode: u8, payload: &[u8], mask: [u8; 4]) -> Vec<u8> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        n if n < 126 => frame.push(0x80 | n as u8),
+        n if n <= u16::MAX as usize => {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(n as u16).to_be_bytes());
+        }
+        n => {
+            frame.push(0x80 | 127);
+            frame.extend_from_slice(&(n as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(&mask);
+    frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+    frame
+}
+
+/// Reads frames from the stream, keeping what was read past the end of the last one.
+struct Reader {
+    buf: Vec<u8>,
+}
+
+impl Reader {
+    async fn fill<S: AsyncRead + Unpin>(&mut self, stream: &mut S, n: usize) -> anyhow::Result<()> {
+        let mut chunk = [0; 4096];
+        while self.buf.len() < n {
+            let read = stream.read(&mut chunk).await?;
+            if read == 0 {
+                return Err(anyhow::anyhow!("Connection closed by the server"));
+            }
+            self.buf.extend_from_slice(&chunk[..read]);
+        }
+        Ok(())
+    }
+
+    /// Read the response to the upgrade request and check that the server switched protocols.
+    async fn upgrade<S: AsyncRead + Unpin>(
+        &mut self,
+        stream: &mut S,
+        key: &str,
+    ) -> anyhow::Result<()> {
+        let mut chunk = [0; 4096];
+        let end = loop {
+            if let Some(end) = self.buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                break end + 4;
+            }
+            let read = stream.read(&mut chunk).await?;
+            if read == 0 {
+                return Err(anyhow::anyhow!("Connection closed during the upgrade"));
+            }
+            self.buf.extend_from_slice(&chunk[..read]);
+        };
+        let head = match http_parser::parse_response_head(&self.buf[..end]) {
+            Some(head) => head,
+            None => return Err(anyhow::anyhow!("Invalid response from server")),
+        };
+        if head.status != 101 {
+            return Err(anyhow::anyhow!(
+                "Upgrade refused with status {}",
+                head.status
+            ));
+        }
+        if head.header("sec-websocket-accept").map(str::trim) != Some(accept_key(key).as_str()) {
+            return Err(anyhow::anyhow!(
+                "Invalid Sec-WebSocket-Accept in the upgrade response"
+            ));
+        }
+        self.buf.drain(..end);
+        Ok(())
+    }
+
+    /// Read one frame, returns whether it is the last of its message, the opcode and the payload.
+    async fn frame<S: AsyncRead + Unpin>(
+        &mut self,
+        stream: &mut S,
+    ) -> anyhow::Result<(bool, u8, Vec<u8>)> {
+        self.fill(stream, 2).await?;
+        let (fin, opcode, masked) = (
+            self.buf[0] & 0x80 != 0,
+            self.buf[0] & 0x0F,
+            self.buf[1] & 0x80 != 0,
+        );
+        let (length, mut at) = match self.buf[1] & 0x7F {
+            126 => {
+                self.fill(stream, 4).await?;
+                (u16::from_be_bytes([self.buf[2], self.buf[3]]) as usize, 4)
+            }
+            127 => {
+                self.fill(stream, 10).await?;
+                let mut bytes = [0; 8];
+                bytes.copy_from_slice(&self.buf[2..10]);
+                (
+                    usize::try_from(u64::from_be_bytes(bytes)).unwrap_or(usize::MAX),
+                    10,
+                )
+            }
+            n => (n as usize, 2),
+        };
+        if length > MAX_MESSAGE {
+            return Err(anyhow::anyhow!("Frame of {} bytes is too large", length));
+        }
+        let mut mask = [0; 4];
+        if masked {
+            self.fill(stream, at + 4).await?;
+            mask.copy_from_slice(&self.buf[at..at + 4]);
+            at += 4;
+        }
+        let end = match at.checked_add(length) {
+            Some(end) => end,
+            None => return Err(anyhow::anyhow!("Frame of {} bytes is too large", length)),
+        };
+        self.fill(stream, end).await?;
+        let payload = self.buf[at..end]
+            .iter()
+            .enumerate()
+            .map(|(i, b)| b ^ mask[i % 4])
+            .collect();
+        self.buf.drain(..end);
+        Ok((fin, opcode, payload))
+    }
+
+    /// Read the next message, answering pings. Returns the opcode and the payload, pongs are
+    /// returned as they are.
+    async fn message<S: AsyncRead + AsyncWrite + Unpin>(
+        &mut self,
+        stream: &mut S,
+    ) -> anyhow::Result<(u8, Vec<u8>)> {
+        let mut message: Option<(u8, Vec<u8>)> = None;
+        loop {
+            let (fin, opcode, payload) = self.frame(stream).await?;
+            match opcode {
+                PING => {
+                    stream
+                        .write_all(&encode_frame(PONG, &payload, random()))
+                        .await?
+                }
+                PONG => return Ok((PONG, payload)),
+                CLOSE => return Err(anyhow::anyhow!("Connection closed by the server")),
+                CONTINUATION => match &mut message {
+                    Some((_, data)) if data.len() + payload.len() > MAX_MESSAGE => {
+                        return Err(anyhow::anyhow!(
+                            "Message is larger than {} bytes",
+                            MAX_MESSAGE
+                        ))
+                    }
+                    Some((_, data)) => data.extend_from_slice(&payload),
+                    None => return Err(anyhow::anyhow!("Continuation frame without a message")),
+                },
+                _ => message = Some((opcode, payload)),
+            }
+            if fin && opcode != PING {
+                if let Some(message) = message.take() {
+                    return Ok(message);
+                }
+            }
+        }
+    }
+}
+
+/// Open a connection, upgrade it and exchange the messages.
+async fn session<E: Executor>(
+    executor: &E,
+    head: &[u8],
+    options: &Options,
+) -> anyhow::Result<Session>
+where
+    E::Connection: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut stats = Stats::default();
+    let mut con = executor.connect(&mut stats).await?;
+    let key = base64::encode(random::<16>());
+    let start = Instant::now();
+    con.write_all(head).await?;
+    con.write_all(format!("\r\nSec-WebSocket-Key: {}\r\n\r\n", key).as_bytes())
+        .await?;
+    let mut reader = Reader { buf: vec![] };
+    reader.upgrade(&mut con, &key).await?;
+    let mut session = Session {
+        connect: stats.connect,
+        proxy: stats.proxy,
+        handshake: stats.handshake,
+        upgrade: start.elapsed().as_millis(),
+        ..Session::default()
+    };
+    let sending = Instant::now();
+    for i in 0..options.messages {
+        if let Some(rate) = options.rate {
+            let due = Duration::from_secs_f64(i as f64 / rate);
+            if let Some(wait) = due.checked_sub(sending.elapsed()) {
+                tokio::time::delay_for(wait).await;
+            }
+        }
+        let sent = Instant::now();
+        con.write_all(&encode_frame(TEXT, &options.payload, random()))
+            .await?;
+        // pongs are not echoes of the message
+        while reader.message(&mut con).await?.0 == PONG {}
+        session.latencies.push(sent.elapsed().as_millis());
+    }
+    if options.hold > Duration::from_secs(0) {
+        tokio::time::delay_for(options.hold).await;
+        session.held = con
+            .write_all(&encode_frame(PING, b"bust", random()))
+            .await
+            .is_ok()
+            && matches!(reader.message(&mut con).await, Ok((PONG, _)));
+    }
+    let _ = con
+        .write_all(&encode_frame(CLOSE, &1000u16.to_be_bytes(), random()))
+        .await;
+    executor.close(con).await;
+    Ok(session)
+}
+
+/// Open `concurrency` connections at a time until the limits are reached or the run is interrupted.
+/// Results are in the order the connections finished.
+pub async fn benchmark<E: Executor>(
+    executor: &E,
+    head: &[u8],
+    options: &Options,
+    interrupt: &Interrupt,
+    progress: &Progress,
+) -> Vec<anyhow::Result<Session>>
+where
+    E::Connection: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut sessions = vec![];
+    schedule::run(
+        &options.limits,
+        Some(interrupt),
+        |_| async {
+            let result = session(executor, head, options).await;
+            progress.record(result.as_ref().ok().map(|s| s.upgrade));
+            result
+        },
+        |done| sessions.extend(done),
+    )
+    .await;
+    sessions
+}
+
+/// Print the report of the connections.
+pub fn print(sessions: &[anyhow::Result<Session>], options: &Options, run_time: f64, tls: bool) {
+    let upgraded: Vec<&Session> = sessions.iter().filter_map(|s| s.as_ref().ok()).collect();
+    let mut latencies: Vec<u128> = upgraded
+        .iter()
+        .flat_map(|s| s.latencies.iter().copied())
+        .collect();
+    latencies.sort_unstable();
+    println!(" Number of Connections       : {}", sessions.len());
+    println!(" Number of Upgraded          : {}", upgraded.len());
+    println!(
+        " Number of Failed            : {}",
+        sessions.len() - upgraded.len()
+    );
+    if let Some(Err(err)) = sessions.iter().find(|s| s.is_err()) {
+        println!(" First Error                 : {}", err);
+    }
+    println!(" Messages Echoed             : {}", latencies.len());
+    println!(
+        " Messages per second         : {:.2}",
+        latencies.len() as f64 / run_time
+    );
+    if options.hold > Duration::from_secs(0) {
+        let label = format!("Held Open for {}s", options.hold.as_secs());
+        println!(
+            " {:<28}: {}",
+            label,
+            upgraded.iter().filter(|s| s.held).count()
+        );
+    }
+    let column =
+        |phase: fn(&Session) -> u128| upgraded.iter().map(|s| phase(s)).collect::<Vec<u128>>();
+    let mut rows = vec![("Connection Time", column(|s| s.connect))];
+    if upgraded.iter().any(|s| s.proxy > 0) {
+        rows.push(("Proxy Tunnel Setup", column(|s| s.proxy)));
+    }
+    if tls {
+        rows.push(("Tls Handshake Time", column(|s| s.handshake)));
+    }
+    rows.push(("Websocket Upgrade", column(|s| s.upgrade)));
+    rows.push(("Message Round Trip", latencies.clone()));
+    println!();
+    tables::create_phase_table(&rows);
+    if latencies.is_empty() {
+        println!("\nNo message was echoed");
+    } else {
+        println!("\nApprox time Required for the echo of % of messages");
+        tables::create_percent_table(&latencies);
+    }
+}